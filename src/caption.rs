@@ -0,0 +1,217 @@
+use Sei;
+
+// The ITU-T T.35 header for ATSC A/53 caption data.
+const HEADER: [u8; 8] = [0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03];
+// The most caption triplets a single frame can hold.
+const MAX: usize = 31;
+// The header, the count, the padding, the triplets and the marker bits.
+const SIZE: usize = 8 + 2 + 3 * MAX + 1;
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[repr(u8)]
+/// The kind of data in a caption triplet.
+pub enum CaptionType {
+    /// A CEA-608 byte pair for the first field.
+    Field1 = 0,
+    /// A CEA-608 byte pair for the second field.
+    Field2 = 1,
+    /// A continuation of a CEA-708 (DTVCC) packet.
+    DtvccData = 2,
+    /// The start of a CEA-708 (DTVCC) packet.
+    DtvccStart = 3,
+}
+
+/// The closed captions for a single frame, which are embedded as an ATSC A/53
+/// `cc_data` SEI message.
+///
+/// They're attached to the frame they're encoded with, so they follow that
+/// frame through any reordering, and are shown along with it.
+///
+/// ```rust
+/// # use x264::{Captions, FrameOptions};
+/// let mut captions = Captions::new();
+/// captions.cea608(&[[0x94, 0x2c], [0x94, 0x2c]]);
+/// let options = FrameOptions::new().captions(&captions);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Captions {
+    buf: [u8; SIZE],
+    count: usize,
+}
+
+impl Captions {
+    /// Makes an empty set of captions.
+    pub fn new() -> Self {
+        let mut buf = [0; SIZE];
+        buf[..HEADER.len()].copy_from_slice(&HEADER);
+
+        let mut captions = Captions { buf, count: 0 };
+        captions.finish();
+        captions
+    }
+
+    /// Makes a set of captions from `cc_data` triplets, as found in other
+    /// streams, whose marker and validity bits are already filled in.
+    ///
+    /// Returns `None` if the data isn't made of whole triplets, or if there
+    /// are too many of them.
+    pub fn from_cc_data(data: &[u8]) -> Option<Self> {
+        if data.len() % 3 != 0 || data.len() / 3 > MAX {
+            return None;
+        }
+
+        let mut captions = Self::new();
+        let start = HEADER.len() + 2;
+        captions.buf[start..start + data.len()].copy_from_slice(data);
+        captions.count = data.len() / 3;
+        captions.finish();
+        Some(captions)
+    }
+
+    /// Adds a single triplet.
+    ///
+    /// Returns `false` if there's no room left.
+    pub fn push(&mut self, kind: CaptionType, data: [u8; 2]) -> bool {
+        if self.count == MAX {
+            return false;
+        }
+
+        let i = HEADER.len() + 2 + 3 * self.count;
+        self.buf[i] = 0xFC | kind as u8;
+        self.buf[i + 1] = data[0];
+        self.buf[i + 2] = data[1];
+        self.count += 1;
+        self.finish();
+        true
+    }
+
+    /// Adds CEA-608 byte pairs for the first field.
+    ///
+    /// Returns `false` if they didn't all fit.
+    pub fn cea608(&mut self, pairs: &[[u8; 2]]) -> bool {
+        pairs.iter().all(|&pair| self.push(CaptionType::Field1, pair))
+    }
+
+    /// Adds a CEA-708 (DTVCC) packet, padding it to an even length.
+    ///
+    /// Returns `false` if it didn't fit, in which case nothing is added.
+    pub fn cea708(&mut self, packet: &[u8]) -> bool {
+        if self.count + (packet.len() + 1) / 2 > MAX {
+            return false;
+        }
+
+        for (i, chunk) in packet.chunks(2).enumerate() {
+            let kind = if i == 0 {
+                CaptionType::DtvccStart
+            } else {
+                CaptionType::DtvccData
+            };
+
+            let pair = [chunk[0], if chunk.len() > 1 { chunk[1] } else { 0 }];
+            self.push(kind, pair);
+        }
+
+        true
+    }
+
+    /// The number of triplets.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether there are no triplets.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The SEI message that carries the captions.
+    pub fn sei(&self) -> Sei {
+        Sei::registered(self.bytes())
+    }
+
+    // The payload of the SEI message.
+    fn bytes(&self) -> &[u8] {
+        &self.buf[..HEADER.len() + 2 + 3 * self.count + 1]
+    }
+
+    // Updates the count and the trailing marker bits.
+    fn finish(&mut self) {
+        self.buf[HEADER.len()] = 0x40 | self.count as u8;
+        self.buf[HEADER.len() + 1] = 0;
+        self.buf[HEADER.len() + 2 + 3 * self.count] = 0xFF;
+    }
+}
+
+impl Default for Captions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let captions = Captions::new();
+        assert_eq!(captions.bytes(), &[
+            0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03,
+            0x40, 0x00,
+            0xFF,
+        ]);
+    }
+
+    #[test]
+    fn cea608() {
+        let mut captions = Captions::new();
+        assert!(captions.cea608(&[[0x94, 0x2c], [0x94, 0x2f]]));
+        assert_eq!(captions.bytes(), &[
+            0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03,
+            0x42, 0x00,
+            0xFC, 0x94, 0x2c,
+            0xFC, 0x94, 0x2f,
+            0xFF,
+        ]);
+    }
+
+    #[test]
+    fn cea708_odd_length() {
+        let mut captions = Captions::new();
+        assert!(captions.cea708(&[0x03, 0x12, 0x34]));
+        assert_eq!(captions.bytes(), &[
+            0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03,
+            0x42, 0x00,
+            0xFF, 0x03, 0x12,
+            0xFE, 0x34, 0x00,
+            0xFF,
+        ]);
+    }
+
+    #[test]
+    fn cea708_too_long() {
+        let mut captions = Captions::new();
+        assert!(captions.cea608(&[[0x80, 0x80]; 30]));
+        assert!(!captions.cea708(&[0x03, 0x12, 0x34]));
+        assert_eq!(captions.len(), 30);
+    }
+
+    #[test]
+    fn full() {
+        let mut captions = Captions::new();
+        assert!(captions.cea608(&[[0x80, 0x80]; 31]));
+        assert!(!captions.push(CaptionType::Field2, [0x80, 0x80]));
+        assert_eq!(captions.bytes()[8], 0x40 | 31);
+        assert_eq!(captions.bytes().len(), 8 + 2 + 3 * 31 + 1);
+    }
+
+    #[test]
+    fn from_cc_data() {
+        let data = [0xFC, 0x94, 0x2c, 0xF9, 0x80, 0x80];
+        let captions = Captions::from_cc_data(&data).unwrap();
+        assert_eq!(captions.len(), 2);
+        assert_eq!(&captions.bytes()[10..16], &data);
+        assert!(Captions::from_cc_data(&data[..4]).is_none());
+        assert!(Captions::from_cc_data(&[0xFC; 3 * 32]).is_none());
+    }
+}
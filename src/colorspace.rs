@@ -67,7 +67,9 @@ impl Encoding {
     /// Gets the colorspace of the encoding.
     pub fn colorspace(self) -> Colorspace {
         use core::mem;
-        unsafe { mem::transmute(self.raw as u32 % X264_CSP_MAX) }
+        // The modifiers are flags above the colorspace bits.
+        let csp = self.raw as u32 & X264_CSP_MASK;
+        unsafe { mem::transmute(csp % X264_CSP_MAX) }
     }
 
     #[doc(hidden)]
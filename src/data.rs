@@ -34,32 +34,22 @@ impl<'a> Data<'a> {
     /// Panics if `i` is out-of-bounds. In order to be within the bounds,
     /// `i` must be less than `len`.
     pub fn unit(&self, i: usize) -> Unit<'a> {
-        const D: i32 = Priority::Disposable as i32;
-        const L: i32 = Priority::Low as i32;
-        const H: i32 = Priority::High as i32;
-
         assert!(i < self.len);
+        unsafe { Unit::from_raw(*self.ptr.offset(i as isize)) }
+    }
 
-        let nal = unsafe {
-            *self.ptr.offset(i as isize)
-        };
-
-        Unit {
-            priority:
-                match nal.i_ref_idc {
-                    D => Priority::Disposable,
-                    L => Priority::Low,
-                    H => Priority::High,
-                    _ => Priority::Highest,
-                },
-            payload:
-                unsafe {
-                    slice::from_raw_parts(
-                        nal.p_payload,
-                        nal.i_payload as usize
-                    )
-                }
+    /// The index of the slice that the `i`th unit belongs to, counting from
+    /// zero within this data, or `None` if the unit isn't a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out-of-bounds.
+    pub fn slice(&self, i: usize) -> Option<usize> {
+        if !self.unit(i).kind().is_slice() {
+            return None;
         }
+
+        Some((0..i).filter(|&j| self.unit(j).kind().is_slice()).count())
     }
 
     /// The entire chunk of data, as one big byte-slice.
@@ -86,14 +76,61 @@ impl<'a> Data<'a> {
 /// A single NAL unit.
 pub struct Unit<'a> {
     priority: Priority,
+    kind: UnitType,
+    first_mb: i32,
+    last_mb: i32,
     payload: &'a [u8]
 }
 
 impl<'a> Unit<'a> {
+    #[doc(hidden)]
+    pub unsafe fn from_raw(nal: x264_nal_t) -> Self {
+        Unit {
+            priority: Priority::from_raw(nal.i_ref_idc),
+            kind: UnitType::from_raw(nal.i_type),
+            first_mb: nal.i_first_mb,
+            last_mb: nal.i_last_mb,
+            payload: slice::from_raw_parts(
+                nal.p_payload,
+                nal.i_payload as usize
+            ),
+        }
+    }
+
+    pub(crate) fn new(
+        priority: Priority,
+        kind: UnitType,
+        (first_mb, last_mb): (i32, i32),
+        payload: &'a [u8],
+    ) -> Self {
+        Unit { priority, kind, first_mb, last_mb, payload }
+    }
+
     /// How crucial this unit is regarding the decoding of the video.
     pub fn priority(&self) -> Priority {
         self.priority
     }
+
+    /// What kind of data the unit holds.
+    pub fn kind(&self) -> UnitType {
+        self.kind
+    }
+
+    /// The first macroblock of the slice, in raster order.
+    ///
+    /// This is meaningless if the unit isn't a slice. It's useful for putting
+    /// slices back in order, since sliced threads may finish them out of
+    /// order.
+    pub fn first_mb(&self) -> i32 {
+        self.first_mb
+    }
+
+    /// The last macroblock of the slice, in raster order.
+    ///
+    /// This is meaningless if the unit isn't a slice.
+    pub fn last_mb(&self) -> i32 {
+        self.last_mb
+    }
 }
 
 impl<'a> AsRef<[u8]> for Unit<'a> {
@@ -103,6 +140,7 @@ impl<'a> AsRef<[u8]> for Unit<'a> {
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(i32)]
 /// The importance of a given unit.
 pub enum Priority {
@@ -115,3 +153,88 @@ pub enum Priority {
     /// Extremely important.
     Highest = nal_priority_e::NAL_PRIORITY_HIGHEST as i32,
 }
+
+impl Priority {
+    fn from_raw(raw: i32) -> Self {
+        const D: i32 = Priority::Disposable as i32;
+        const L: i32 = Priority::Low as i32;
+        const H: i32 = Priority::High as i32;
+
+        match raw {
+            D => Priority::Disposable,
+            L => Priority::Low,
+            H => Priority::High,
+            _ => Priority::Highest,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// The kind of data held by a given unit.
+pub enum UnitType {
+    /// Something x264 didn't say.
+    Unknown = nal_unit_type_e::NAL_UNKNOWN as i32,
+    /// A slice of a frame.
+    Slice = nal_unit_type_e::NAL_SLICE as i32,
+    /// The first partition of a slice.
+    SliceA = nal_unit_type_e::NAL_SLICE_DPA as i32,
+    /// The second partition of a slice.
+    SliceB = nal_unit_type_e::NAL_SLICE_DPB as i32,
+    /// The third partition of a slice.
+    SliceC = nal_unit_type_e::NAL_SLICE_DPC as i32,
+    /// A slice of an IDR frame, from which decoding can start.
+    SliceIdr = nal_unit_type_e::NAL_SLICE_IDR as i32,
+    /// Supplemental enhancement information.
+    Sei = nal_unit_type_e::NAL_SEI as i32,
+    /// A sequence parameter set.
+    Sps = nal_unit_type_e::NAL_SPS as i32,
+    /// A picture parameter set.
+    Pps = nal_unit_type_e::NAL_PPS as i32,
+    /// An access unit delimiter.
+    Aud = nal_unit_type_e::NAL_AUD as i32,
+    /// Filler data.
+    Filler = nal_unit_type_e::NAL_FILLER as i32,
+}
+
+impl UnitType {
+    /// Whether the unit holds (part of) a slice.
+    pub fn is_slice(self) -> bool {
+        match self {
+            UnitType::Slice
+            | UnitType::SliceA
+            | UnitType::SliceB
+            | UnitType::SliceC
+            | UnitType::SliceIdr => true,
+            _ => false,
+        }
+    }
+
+    fn from_raw(raw: i32) -> Self {
+        const SLICE: i32 = UnitType::Slice as i32;
+        const SLICE_A: i32 = UnitType::SliceA as i32;
+        const SLICE_B: i32 = UnitType::SliceB as i32;
+        const SLICE_C: i32 = UnitType::SliceC as i32;
+        const SLICE_IDR: i32 = UnitType::SliceIdr as i32;
+        const SEI: i32 = UnitType::Sei as i32;
+        const SPS: i32 = UnitType::Sps as i32;
+        const PPS: i32 = UnitType::Pps as i32;
+        const AUD: i32 = UnitType::Aud as i32;
+        const FILLER: i32 = UnitType::Filler as i32;
+
+        match raw {
+            SLICE     => UnitType::Slice,
+            SLICE_A   => UnitType::SliceA,
+            SLICE_B   => UnitType::SliceB,
+            SLICE_C   => UnitType::SliceC,
+            SLICE_IDR => UnitType::SliceIdr,
+            SEI       => UnitType::Sei,
+            SPS       => UnitType::Sps,
+            PPS       => UnitType::Pps,
+            AUD       => UnitType::Aud,
+            FILLER    => UnitType::Filler,
+            _         => UnitType::Unknown,
+        }
+    }
+}
@@ -1,11 +1,86 @@
-use {Data, Encoding, Error, Image, Picture, Result, Setup};
-use core::{mem, ptr};
+use {Data, Encoding, Error, FrameOptions, FrameType, Image, Picture, Result};
+use Setup;
+#[cfg(feature = "std")]
+use {Logger, Packet, UnitType};
+#[cfg(feature = "std")]
+use setup::UnitSink;
+#[cfg(feature = "std")]
+use std::sync::atomic::Ordering;
+#[cfg(feature = "std")]
+use std::boxed::Box;
+#[cfg(feature = "std")]
+use std::ffi::CString;
+#[cfg(feature = "std")]
+use std::string::String;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+#[cfg(feature = "std")]
+use core::slice;
+use core::{cmp, mem, ptr};
+use core::ffi::c_void;
 use x264::*;
 
 /// Encodes video.
 pub struct Encoder {
     raw: *mut x264_t,
     params: x264_param_t,
+    // The number of frames since the last intra refresh began.
+    refresh: Option<i32>,
+    // Dropped after `raw` is closed, since x264 may point into it.
+    owned: Owned,
+}
+
+/// Whatever x264 points to that has to live as long as the encoder.
+#[derive(Default)]
+pub(crate) struct Owned {
+    #[cfg(feature = "std")]
+    pub logger: Option<Box<Logger>>,
+    #[cfg(feature = "std")]
+    pub on_unit: Option<Box<UnitSink>>,
+    #[cfg(feature = "std")]
+    pub zones: Option<CString>,
+    // The headers, escaped by us when x264 leaves that to the callback.
+    #[cfg(feature = "std")]
+    pub headers: Vec<u8>,
+}
+
+impl Owned {
+    // Where x264 finds the unit callback, if there is one.
+    #[cfg(feature = "std")]
+    fn opaque(&self) -> *mut c_void {
+        match self.on_unit {
+            Some(ref on_unit) =>
+                &**on_unit as *const UnitSink as *mut c_void,
+            None => ptr::null_mut(),
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn opaque(&self) -> *mut c_void {
+        ptr::null_mut()
+    }
+
+    // The units go to the callback, so x264 doesn't return valid ones.
+    #[cfg(feature = "std")]
+    fn data_len(&self, len: i32) -> usize {
+        if self.on_unit.is_some() { 0 } else { len as usize }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn data_len(&self, len: i32) -> usize {
+        len as usize
+    }
+
+    // Keeps the headers from going to the callback, while they're written.
+    #[cfg(feature = "std")]
+    fn writing_headers(&self, writing: bool) {
+        if let Some(ref on_unit) = self.on_unit {
+            on_unit.headers.store(writing, Ordering::SeqCst);
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn writing_headers(&self, _: bool) {}
 }
 
 impl Encoder {
@@ -18,9 +93,13 @@ impl Encoder {
 
     #[doc(hidden)]
     pub unsafe fn from_raw(raw: *mut x264_t) -> Self {
+        Self::from_parts(raw, Owned::default())
+    }
+
+    pub(crate) unsafe fn from_parts(raw: *mut x264_t, owned: Owned) -> Self {
         let mut params = mem::uninitialized();
         x264_encoder_parameters(raw, &mut params);
-        Self { raw, params }
+        Self { raw, params, refresh: None, owned }
     }
 
     /// Feeds a frame to the encoder.
@@ -32,10 +111,26 @@ impl Encoder {
     pub fn encode(&mut self, pts: i64, image: Image)
         -> Result<(Data, Picture)>
     {
+        self.encode_with(pts, image, &FrameOptions::new())
+    }
+
+    /// Feeds a frame to the encoder, along with some options for the frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the image and the encoder
+    /// regarding width, height or colorspace, or if the options give the
+    /// wrong number of quantizer offsets.
+    pub fn encode_with(
+        &mut self,
+        pts: i64,
+        image: Image,
+        options: &FrameOptions,
+    ) -> Result<(Data, Picture)> {
         assert_eq!(image.width(), self.width());
         assert_eq!(image.height(), self.height());
         assert_eq!(image.encoding(), self.encoding());
-        unsafe { self.encode_unchecked(pts, image) }
+        unsafe { self.encode_with_unchecked(pts, image, options) }
     }
 
     /// Feeds a frame to the encoder.
@@ -47,16 +142,34 @@ impl Encoder {
     pub unsafe fn encode_unchecked(&mut self, pts: i64, image: Image)
         -> Result<(Data, Picture)>
     {
+        self.encode_with_unchecked(pts, image, &FrameOptions::new())
+    }
+
+    /// Feeds a frame to the encoder, along with some options for the frame.
+    ///
+    /// # Unsafety
+    ///
+    /// The caller must ensure that the width, height *and* colorspace
+    /// of the image are the same as that of the encoder.
+    pub unsafe fn encode_with_unchecked(
+        &mut self,
+        pts: i64,
+        image: Image,
+        options: &FrameOptions,
+    ) -> Result<(Data, Picture)> {
         let image = image.raw();
 
         let mut picture = mem::uninitialized();
         x264_picture_init(&mut picture);
         picture.i_pts = pts;
         picture.img = image;
+        picture.opaque = self.owned.opaque();
+        options.apply(&mut picture, &self.params);
 
         let mut len = 0;
         let mut stuff = mem::uninitialized();
         let mut raw = mem::uninitialized();
+        x264_picture_init(&mut raw);
 
         let err = x264_encoder_encode(
             self.raw,
@@ -67,19 +180,91 @@ impl Encoder {
         );
 
         if err < 0 {
-            Err(Error)
+            Err(Error::Unknown)
         } else {
-            let data = Data::from_raw_parts(stuff, len as usize);
-            let picture = Picture::from_raw(raw);
+            let data = Data::from_raw_parts(stuff, self.owned.data_len(len));
+            let picture = self.picture(raw, len);
             Ok((data, picture))
         }
     }
 
+    /// Starts an intra refresh with the next P-frame, or as soon as the
+    /// current one finishes, which lets decoders recover from packet loss.
+    ///
+    /// This needs intra refresh to be turned on in the `Setup`. It can be
+    /// combined with a huge keyframe interval, so that refreshes only happen
+    /// when asked for.
+    pub fn intra_refresh(&mut self) -> Result<()> {
+        if self.params.b_intra_refresh == 0 {
+            return Err(Error::Unsupported);
+        }
+
+        unsafe { x264_encoder_intra_refresh(self.raw); }
+        Ok(())
+    }
+
+    /// Stops the encoder from referencing the frame with the given timestamp,
+    /// or any later frame, which lets decoders recover from packet loss
+    /// without waiting for a keyframe.
+    ///
+    /// This can't be used with B-frames or intra refresh, and it works best
+    /// when there are several reference frames, so that some are left over.
+    pub fn invalidate_reference(&mut self, pts: i64) -> Result<()> {
+        if self.params.i_bframe != 0 || self.params.b_intra_refresh != 0 {
+            return Err(Error::Unsupported);
+        }
+
+        let err = unsafe { x264_encoder_invalidate_reference(self.raw, pts) };
+
+        if err < 0 {
+            Err(Error::Unknown)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn picture(&mut self, raw: x264_picture_t, len: i32) -> Picture {
+        let mut picture = unsafe { Picture::from_raw(raw) };
+
+        // Without any units, no frame came out, and the picture means nothing.
+        if self.params.b_intra_refresh == 0 || len == 0 {
+            return picture;
+        }
+
+        if picture.frame_type() == FrameType::Idr {
+            picture.set_refreshed();
+            self.refresh = None;
+            return picture;
+        }
+
+        if picture.keyframe() {
+            self.refresh = Some(0);
+        }
+
+        // The same recovery frame count that x264 puts in its SEI.
+        let mb_width = (self.params.i_width + 15) / 16;
+        let period = cmp::min(mb_width - 1, self.params.i_keyint_max)
+                   + self.params.i_bframe - 1;
+
+        if let Some(n) = self.refresh {
+            if n >= period {
+                picture.set_refreshed();
+                self.refresh = None;
+            } else {
+                self.refresh = Some(n + 1);
+            }
+        }
+
+        picture
+    }
+
     /// Gets the video headers, which should be sent first.
     pub fn headers(&mut self) -> Result<Data> {
         let mut len = 0;
         let mut stuff = unsafe { mem::uninitialized() };
 
+        self.owned.writing_headers(true);
+
         let err = unsafe {
             x264_encoder_headers(
                 self.raw,
@@ -88,13 +273,87 @@ impl Encoder {
             )
         };
 
+        self.owned.writing_headers(false);
+
         if err < 0 {
-            Err(Error)
-        } else {
-            Ok(unsafe { Data::from_raw_parts(stuff, len as usize) })
+            return Err(Error::Unknown);
+        }
+
+        unsafe {
+            self.escape_headers(stuff, len as usize);
+            Ok(Data::from_raw_parts(stuff, len as usize))
+        }
+    }
+
+    // With a unit callback, x264 leaves the units as they are, so they have
+    // to be escaped into a buffer of our own, one after the other.
+    #[cfg(feature = "std")]
+    unsafe fn escape_headers(&mut self, nals: *mut x264_nal_t, len: usize) {
+        if self.owned.on_unit.is_none() {
+            return;
+        }
+
+        let raw = self.raw;
+        let nals = slice::from_raw_parts_mut(nals, len);
+        let buf = &mut self.owned.headers;
+
+        // The size that x264 asks for, so that it has room to escape them.
+        let size = nals.iter()
+            .map(|nal| nal.i_payload as usize * 3 / 2 + 5 + 64)
+            .sum();
+
+        buf.clear();
+        buf.reserve(size);
+
+        let mut offset = 0;
+
+        for nal in nals {
+            x264_nal_encode(raw, buf.as_mut_ptr().offset(offset), nal);
+            offset += nal.i_payload as isize;
         }
     }
 
+    #[cfg(not(feature = "std"))]
+    unsafe fn escape_headers(&mut self, _: *mut x264_nal_t, _: usize) {}
+
+    /// The settings the encoder is actually using, in the same format as the
+    /// options string that x264 embeds in the stream.
+    ///
+    /// ```text
+    /// cabac=1 ref=3 deblock=1:0:0 analyse=0x3:0x113 me=hex subme=7 ...
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn options_string(&mut self) -> Result<String> {
+        const PREFIX: &[u8] = b"options: ";
+
+        let headers = self.headers()?;
+
+        for i in 0..headers.len() {
+            let unit = headers.unit(i);
+
+            if unit.kind() != UnitType::Sei {
+                continue;
+            }
+
+            let payload = unit.as_ref();
+            let start = payload
+                .windows(PREFIX.len())
+                .position(|window| window == PREFIX);
+
+            if let Some(start) = start {
+                let rest = &payload[start + PREFIX.len()..];
+                let end = rest.iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or(rest.len());
+
+                return String::from_utf8(rest[..end].to_vec())
+                    .map_err(|_| Error::Unknown);
+            }
+        }
+
+        Err(Error::Unknown)
+    }
+
     /// Begins flushing the encoder, to handle any delayed frames.
     ///
     /// ```rust
@@ -113,6 +372,45 @@ impl Encoder {
         Flush { encoder: self }
     }
 
+    /// Handles any delayed frames, but keeps the encoder around afterwards.
+    ///
+    /// ```rust
+    /// # use x264::{Colorspace, Setup};
+    /// # let mut encoder = Setup::default().build(Colorspace::RGB, 1920, 1080).unwrap();
+    /// #
+    /// {
+    ///     let mut drain = encoder.drain();
+    ///
+    ///     while let Some(result) = drain.next() {
+    ///         if let Ok((data, picture)) = result {
+    ///             // Handle data.
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// // The encoder is still usable here.
+    /// ```
+    pub fn drain(&mut self) -> Drain {
+        Drain { encoder: self }
+    }
+
+    /// Handles any delayed frames as owned packets, which allows using them
+    /// in `for` loops and with iterator adapters.
+    ///
+    /// ```rust
+    /// # use x264::{Colorspace, Setup};
+    /// # let mut encoder = Setup::default().build(Colorspace::RGB, 1920, 1080).unwrap();
+    /// #
+    /// for packet in encoder.packets() {
+    ///     let packet = packet.unwrap();
+    ///     // Handle the packet.
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn packets(&mut self) -> Packets {
+        Packets { encoder: self }
+    }
+
     /// The width required of any input images.
     pub fn width(&self) -> i32 { self.params.i_width }
     /// The height required of any input images.
@@ -121,8 +419,56 @@ impl Encoder {
     pub fn encoding(&self) -> Encoding {
         unsafe { Encoding::from_raw(self.params.i_csp) }
     }
+
+    /// The number of frames the encoder is currently holding on to.
+    pub fn delayed_frames(&self) -> usize {
+        unsafe { x264_encoder_delayed_frames(self.raw) as usize }
+    }
+
+    /// The most frames the encoder could ever hold on to at once, which
+    /// depends on the B-frames, the lookahead and the threads.
+    pub fn maximum_delayed_frames(&self) -> usize {
+        unsafe { x264_encoder_maximum_delayed_frames(self.raw) as usize }
+    }
+
+    /// The worst-case delay between a frame going in and coming out.
+    ///
+    /// The value is in timebase ticks, and doesn't include encoding time.
+    pub fn latency(&self) -> i64 {
+        let p = &self.params;
+        let num = p.i_fps_den as i64 * p.i_timebase_den as i64;
+        let den = p.i_fps_num as i64 * p.i_timebase_num as i64;
+
+        if den == 0 {
+            0
+        } else {
+            self.maximum_delayed_frames() as i64 * num / den
+        }
+    }
+
+    /// The number of frames that each P-frame can reference.
+    pub fn reference_frames(&self) -> i32 { self.params.i_frame_reference }
+    /// The maximum number of consecutive B-frames.
+    pub fn bframes(&self) -> i32 { self.params.i_bframe }
+    /// The number of frames the rate control looks ahead.
+    pub fn lookahead(&self) -> i32 { self.params.rc.i_lookahead }
+
+    /// The number of threads the encoder actually uses.
+    pub fn threads(&self) -> i32 { self.params.i_threads }
+    /// The number of threads the lookahead actually uses.
+    pub fn lookahead_threads(&self) -> i32 {
+        self.params.i_lookahead_threads
+    }
+    /// Whether the threads work on slices rather than on whole frames.
+    pub fn sliced_threads(&self) -> bool { self.params.b_sliced_threads != 0 }
+    /// Whether the output is independent of the thread count.
+    pub fn deterministic(&self) -> bool { self.params.b_deterministic != 0 }
 }
 
+// x264 keeps no thread-local state, so an encoder may be used from any thread,
+// as long as it's only used from one thread at a time.
+unsafe impl Send for Encoder {}
+
 impl Drop for Encoder {
     fn drop(&mut self) {
         unsafe { x264_encoder_close(self.raw); }
@@ -137,33 +483,84 @@ pub struct Flush {
 impl Flush {
     /// Keeps flushing.
     pub fn next(&mut self) -> Option<Result<(Data, Picture)>> {
-        let enc = self.encoder.raw;
+        unsafe { flush(&mut self.encoder) }
+    }
 
-        if unsafe { x264_encoder_delayed_frames(enc) } == 0 {
-            return None;
-        }
+    /// The encoder being flushed.
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
 
-        let mut len = 0;
-        let mut stuff = unsafe { mem::uninitialized() };
-        let mut raw = unsafe { mem::uninitialized() };
+    /// Stops flushing, giving back the encoder.
+    pub fn into_encoder(self) -> Encoder {
+        self.encoder
+    }
+}
 
-        let err = unsafe {
-            x264_encoder_encode(
-                enc,
-                &mut stuff,
-                &mut len,
-                ptr::null_mut(),
-                &mut raw
-            )
-        };
+/// Iterate through any delayed frames, without giving up the encoder.
+pub struct Drain<'a> {
+    encoder: &'a mut Encoder,
+}
 
-        Some(if err < 0 {
-            Err(Error)
-        } else {
-            Ok(unsafe {(
-                Data::from_raw_parts(stuff, len as usize),
-                Picture::from_raw(raw),
-            )})
-        })
+impl<'a> Drain<'a> {
+    /// Keeps draining.
+    pub fn next(&mut self) -> Option<Result<(Data, Picture)>> {
+        unsafe { flush(self.encoder) }
+    }
+}
+
+/// Iterate through any delayed frames as owned packets.
+#[cfg(feature = "std")]
+pub struct Packets<'a> {
+    encoder: &'a mut Encoder,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Result<Packet>> {
+        loop {
+            match unsafe { flush(self.encoder) }? {
+                Ok((data, picture)) => {
+                    if let Some(packet) = Packet::new(&data, &picture) {
+                        return Some(Ok(packet));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
     }
 }
+
+unsafe fn flush<'a>(encoder: &mut Encoder)
+    -> Option<Result<(Data<'a>, Picture)>>
+{
+    let enc = encoder.raw;
+
+    if x264_encoder_delayed_frames(enc) == 0 {
+        return None;
+    }
+
+    let mut len = 0;
+    let mut stuff = mem::uninitialized();
+    let mut raw = mem::uninitialized();
+    x264_picture_init(&mut raw);
+
+    let err = x264_encoder_encode(
+        enc,
+        &mut stuff,
+        &mut len,
+        ptr::null_mut(),
+        &mut raw
+    );
+
+    Some(if err < 0 {
+        Err(Error::Unknown)
+    } else {
+        Ok((
+            Data::from_raw_parts(stuff, encoder.owned.data_len(len)),
+            encoder.picture(raw, len),
+        ))
+    })
+}
@@ -1,8 +1,66 @@
-use core::result;
+use core::{fmt, result};
 
 #[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
 /// Indicates that something bad happened.
-pub struct Error;
+pub enum Error {
+    /// x264 failed without saying why.
+    Unknown,
+    /// The profile doesn't allow the chosen colorspace, bit depth or
+    /// features.
+    Profile,
+    /// The video needs more decoder resources than the level allows.
+    Level,
+    /// x264 doesn't know of an option with the given name.
+    UnknownOption,
+    /// The option's value doesn't make sense.
+    BadValue,
+    /// The encoder wasn't set up to do that.
+    Unsupported,
+    /// The frame size doesn't work with the interlacing or cropping.
+    Size,
+    /// Blu-ray or AVC-Intra doesn't allow the format.
+    Compliance(Violation),
+}
+
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+/// The part of the format that Blu-ray or AVC-Intra doesn't allow.
+pub enum Violation {
+    /// The colorspace or bit depth.
+    Colorspace,
+    /// The width and height.
+    Size,
+    /// The framerate, at that size.
+    Framerate,
+    /// Whether the video is interlaced, at that size and framerate.
+    Scan,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Unknown => "x264 failed",
+            Error::Profile =>
+                "the profile doesn't support the requested features",
+            Error::Level =>
+                "the video doesn't fit in the requested level",
+            Error::UnknownOption => "unknown option",
+            Error::BadValue => "bad option value",
+            Error::Unsupported => "the encoder wasn't set up to do that",
+            Error::Size => "the frame size isn't allowed",
+            Error::Compliance(Violation::Colorspace) =>
+                "the colorspace isn't allowed on Blu-ray or in AVC-Intra",
+            Error::Compliance(Violation::Size) =>
+                "the frame size isn't allowed on Blu-ray or in AVC-Intra",
+            Error::Compliance(Violation::Framerate) =>
+                "the framerate isn't allowed on Blu-ray or in AVC-Intra",
+            Error::Compliance(Violation::Scan) =>
+                "the interlacing isn't allowed on Blu-ray or in AVC-Intra",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
 
 /// A specialized Result type for video encoding operations.
 pub type Result<T> = result::Result<T, Error>;
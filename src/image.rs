@@ -23,25 +23,8 @@ impl<'a> Image<'a> {
         height: i32,
         planes: &[Plane<'a>],
     ) -> Self {
-        //TODO: Get someone who knows what they're doing to verify this.
-
-        use self::Colorspace::*;
-
         let format = format.into();
-
-        let (pc, wm, hm, ws, hs): (_, _, _, &[_], &[_]) =
-            match format.colorspace() {
-                I420 | YV12 => (3, 2, 2, &[2, 1, 1], &[2, 1, 1]),
-                NV12 | NV21 => (2, 2, 2, &[2, 2],    &[2, 1]   ),
-                I422 | YV16 => (3, 2, 1, &[2, 1, 1], &[1, 1, 1]),
-                NV16        => (2, 2, 1, &[2, 2],    &[1, 1]   ),
-                #[cfg(feature = "yuyv")]
-                YUYV | UYVY => (1, 1, 1, &[2],       &[1]      ),
-                V210        => (1, 1, 1, &[4],       &[1]      ),
-                I444 | YV24 => (3, 1, 1, &[1, 1, 1], &[1, 1, 1]),
-                BGR  | RGB  => (1, 1, 1, &[3],       &[1]      ),
-                BGRA        => (1, 1, 1, &[4],       &[1]      ),
-            };
+        let (pc, wm, hm, ws, hs) = layout(format.colorspace());
 
         let (wq, wr) = (width  / wm, width  % wm);
         let (hq, hr) = (height / hm, height % hm);
@@ -127,6 +110,36 @@ impl<'a> Image<'a> {
     pub fn raw(&self) -> x264_image_t { self.raw }
 }
 
+// Whether an image of this height can be split into two fields, which needs
+// each field to have a whole number of chroma rows.
+pub(crate) fn interlaceable(format: Encoding, height: i32) -> bool {
+    let (_, _, hm, _, _) = layout(format.colorspace());
+    height % (2 * hm) == 0
+}
+
+// The number of planes, the width and height multiples, and the horizontal
+// and vertical scale of each plane.
+fn layout(colorspace: Colorspace)
+    -> (usize, i32, i32, &'static [i32], &'static [i32])
+{
+    //TODO: Get someone who knows what they're doing to verify this.
+
+    use self::Colorspace::*;
+
+    match colorspace {
+        I420 | YV12 => (3, 2, 2, &[2, 1, 1], &[2, 1, 1]),
+        NV12 | NV21 => (2, 2, 2, &[2, 2],    &[2, 1]   ),
+        I422 | YV16 => (3, 2, 1, &[2, 1, 1], &[1, 1, 1]),
+        NV16        => (2, 2, 1, &[2, 2],    &[1, 1]   ),
+        #[cfg(feature = "yuyv")]
+        YUYV | UYVY => (1, 1, 1, &[2],       &[1]      ),
+        V210        => (1, 1, 1, &[4],       &[1]      ),
+        I444 | YV24 => (3, 1, 1, &[1, 1, 1], &[1, 1, 1]),
+        BGR  | RGB  => (1, 1, 1, &[3],       &[1]      ),
+        BGRA        => (1, 1, 1, &[4],       &[1]      ),
+    }
+}
+
 /// A single plane of an image.
 pub struct Plane<'a> {
     /// The plane's stride (the number of bytes for each row).
@@ -3,22 +3,43 @@
 #![no_std]
 #![warn(missing_docs)]
 
+#[cfg(any(feature = "std", test))]
+#[macro_use]
+extern crate std;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 extern crate x264_sys;
 
 use x264_sys::x264;
 
+mod caption;
 mod colorspace;
 mod data;
 mod encoder;
 mod error;
 mod image;
+mod options;
+#[cfg(feature = "std")]
+mod packet;
 mod picture;
 mod setup;
+#[cfg(feature = "std")]
+mod worker;
 
+pub use caption::*;
 pub use colorspace::*;
 pub use data::*;
 pub use encoder::*;
 pub use error::*;
 pub use image::*;
+pub use options::*;
+#[cfg(feature = "std")]
+pub use packet::*;
 pub use picture::*;
 pub use setup::*;
+#[cfg(feature = "std")]
+pub use worker::*;
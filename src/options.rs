@@ -0,0 +1,329 @@
+use Captions;
+use core::ffi::c_void;
+use core::{cmp, mem, ptr, slice};
+use x264::*;
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+/// Extra settings for a single frame.
+///
+/// ```rust
+/// # use x264::{FrameOptions, Sei, Uuid};
+/// let uuid = Uuid::parse("6e3f0b2c-4f1d-4c2a-9b8e-1a2b3c4d5e6f").unwrap();
+/// let sei = [Sei::unregistered(uuid, b"timing metadata")];
+/// let options = FrameOptions::new().sei(&sei);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct FrameOptions<'a> {
+    sei: &'a [Sei<'a>],
+    captions: Option<&'a Captions>,
+    regions: &'a [Region],
+    quant_offsets: Option<&'a [f32]>,
+    pic_struct: Option<PicStruct>,
+}
+
+impl<'a> FrameOptions<'a> {
+    /// Makes a new set of options, which change nothing yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Embeds some SEI messages in the frame.
+    pub fn sei(mut self, sei: &'a [Sei<'a>]) -> Self {
+        self.sei = sei;
+        self
+    }
+
+    /// Embeds closed captions in the frame.
+    pub fn captions(mut self, captions: &'a Captions) -> Self {
+        self.captions = Some(captions);
+        self
+    }
+
+    /// Changes the quantizer of some regions of the frame, to spend more or
+    /// fewer bits on them.
+    ///
+    /// Where regions overlap, the later one wins. Adaptive quantization must
+    /// be turned on for this to work.
+    pub fn regions(mut self, regions: &'a [Region]) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Changes the quantizer of each macroblock, given in raster order.
+    ///
+    /// This is applied before any regions, and adaptive quantization must be
+    /// turned on for it to work.
+    ///
+    /// # Panics
+    ///
+    /// Encoding panics if there isn't exactly one offset per macroblock.
+    pub fn quant_offsets(mut self, offsets: &'a [f32]) -> Self {
+        self.quant_offsets = Some(offsets);
+        self
+    }
+
+    /// How the frame should be displayed, if the encoder was set up to
+    /// signal it.
+    pub fn pic_struct(mut self, pic_struct: PicStruct) -> Self {
+        self.pic_struct = Some(pic_struct);
+        self
+    }
+
+    #[doc(hidden)]
+    pub unsafe fn apply(
+        &self,
+        picture: &mut x264_picture_t,
+        params: &x264_param_t,
+    ) {
+        if self.quant_offsets.is_some() || !self.regions.is_empty() {
+            picture.prop.quant_offsets = self.build_offsets(params);
+            picture.prop.quant_offsets_free = Some(free);
+        }
+
+        if let Some(pic_struct) = self.pic_struct {
+            picture.i_pic_struct = pic_struct as i32;
+        }
+
+        let captions = self.captions
+            .filter(|captions| !captions.is_empty())
+            .map(|captions| captions.sei());
+
+        let count = self.sei.len() + captions.iter().count();
+
+        if count > 0 {
+            let sei = self.sei.iter().chain(captions.iter());
+            picture.extra_sei = to_raw(sei, count);
+        }
+    }
+
+    // Like the SEI, the offsets are freed by x264 once it's done with them.
+    unsafe fn build_offsets(&self, params: &x264_param_t) -> *mut f32 {
+        let interlaced = params.b_interlaced != 0
+                      || params.b_fake_interlaced != 0;
+
+        let mb_width = (params.i_width + 15) / 16;
+        let mb_height = if interlaced {
+            (params.i_height + 31) / 32 * 2
+        } else {
+            (params.i_height + 15) / 16
+        };
+
+        let count = (mb_width * mb_height) as usize;
+
+        if let Some(grid) = self.quant_offsets {
+            assert_eq!(grid.len(), count);
+        }
+
+        let ptr = alloc(count * mem::size_of::<f32>()) as *mut f32;
+        let offsets = slice::from_raw_parts_mut(ptr, count);
+
+        match self.quant_offsets {
+            Some(grid) => offsets.copy_from_slice(grid),
+            None => {
+                for offset in offsets.iter_mut() {
+                    *offset = 0.0;
+                }
+            }
+        }
+
+        for region in self.regions {
+            if region.width <= 0 || region.height <= 0 {
+                continue;
+            }
+
+            let right = cmp::max(region.x + region.width, 0);
+            let bottom = cmp::max(region.y + region.height, 0);
+
+            let x0 = cmp::max(region.x, 0) / 16;
+            let y0 = cmp::max(region.y, 0) / 16;
+            let x1 = cmp::min((right + 15) / 16, mb_width);
+            let y1 = cmp::min((bottom + 15) / 16, mb_height);
+
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    offsets[(y * mb_width + x) as usize] = region.offset;
+                }
+            }
+        }
+
+        ptr
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[repr(i32)]
+/// How a frame is displayed, as fields or repeated frames.
+pub enum PicStruct {
+    /// Let x264 decide.
+    Auto = pic_struct_e::PIC_STRUCT_AUTO as i32,
+    /// A whole frame.
+    Progressive = pic_struct_e::PIC_STRUCT_PROGRESSIVE as i32,
+    /// The top field, then the bottom field.
+    TopBottom = pic_struct_e::PIC_STRUCT_TOP_BOTTOM as i32,
+    /// The bottom field, then the top field.
+    BottomTop = pic_struct_e::PIC_STRUCT_BOTTOM_TOP as i32,
+    /// The top field, the bottom field, then the top field again.
+    TopBottomTop = pic_struct_e::PIC_STRUCT_TOP_BOTTOM_TOP as i32,
+    /// The bottom field, the top field, then the bottom field again.
+    BottomTopBottom = pic_struct_e::PIC_STRUCT_BOTTOM_TOP_BOTTOM as i32,
+    /// The whole frame, shown twice.
+    Double = pic_struct_e::PIC_STRUCT_DOUBLE as i32,
+    /// The whole frame, shown three times.
+    Triple = pic_struct_e::PIC_STRUCT_TRIPLE as i32,
+}
+
+/// A rectangle of the frame whose quantizer should change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    /// The left edge, in pixels.
+    pub x: i32,
+    /// The top edge, in pixels.
+    pub y: i32,
+    /// The width, in pixels.
+    pub width: i32,
+    /// The height, in pixels.
+    pub height: i32,
+    /// The change to the quantizer, where negative means better quality.
+    ///
+    /// It's applied to every macroblock that the region touches.
+    pub offset: f32,
+}
+
+/// A SEI message to embed in the stream.
+#[derive(Clone, Copy, Debug)]
+pub struct Sei<'a> {
+    payload_type: i32,
+    uuid: Option<Uuid>,
+    data: &'a [u8],
+}
+
+impl<'a> Sei<'a> {
+    /// A message with the given payload type and payload.
+    pub fn new(payload_type: i32, data: &'a [u8]) -> Self {
+        Sei { payload_type, uuid: None, data }
+    }
+
+    /// A `user_data_registered_itu_t_t35` message, whose data starts with
+    /// the ITU-T T.35 country code.
+    pub fn registered(data: &'a [u8]) -> Self {
+        Self::new(4, data)
+    }
+
+    /// A `user_data_unregistered` message, whose data is identified by the
+    /// given UUID.
+    pub fn unregistered(uuid: Uuid, data: &'a [u8]) -> Self {
+        Sei { payload_type: 5, uuid: Some(uuid), data }
+    }
+
+    /// The payload type.
+    pub fn payload_type(&self) -> i32 {
+        self.payload_type
+    }
+
+    fn len(&self) -> usize {
+        self.data.len() + if self.uuid.is_some() { 16 } else { 0 }
+    }
+}
+
+/// A UUID, which identifies the format of unregistered SEI data.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    /// Parses a UUID in its usual hexadecimal form, with or without hyphens.
+    pub fn parse(s: &str) -> Option<Uuid> {
+        let mut bytes = [0; 16];
+        let mut digits = s.chars().filter(|&c| c != '-');
+
+        for byte in bytes.iter_mut() {
+            let hi = digits.next()?.to_digit(16)?;
+            let lo = digits.next()?.to_digit(16)?;
+            *byte = (hi << 4 | lo) as u8;
+        }
+
+        if digits.next().is_some() {
+            None
+        } else {
+            Some(Uuid(bytes))
+        }
+    }
+}
+
+// The payloads are copied into memory that x264 frees by itself, since it may
+// hold on to them for several calls to the encoder.
+unsafe fn to_raw<'a, I>(sei: I, count: usize) -> x264_sei_t
+where
+    I: Iterator<Item = &'a Sei<'a>>,
+{
+    let size = count * mem::size_of::<x264_sei_payload_t>();
+    let payloads = alloc(size) as *mut x264_sei_payload_t;
+
+    for (i, sei) in sei.take(count).enumerate() {
+        let payload = alloc(sei.len()) as *mut u8;
+        let mut offset = 0;
+
+        if let Some(Uuid(ref uuid)) = sei.uuid {
+            ptr::copy_nonoverlapping(uuid.as_ptr(), payload, 16);
+            offset = 16;
+        }
+
+        ptr::copy_nonoverlapping(
+            sei.data.as_ptr(),
+            payload.offset(offset),
+            sei.data.len()
+        );
+
+        *payloads.offset(i as isize) = x264_sei_payload_t {
+            payload_size: sei.len() as i32,
+            payload_type: sei.payload_type,
+            payload,
+        };
+    }
+
+    x264_sei_t {
+        num_payloads: count as i32,
+        payloads,
+        sei_free: Some(free),
+    }
+}
+
+unsafe fn alloc(size: usize) -> *mut c_void {
+    // Allocation failure can't be reported through x264 anyway.
+    let ptr = malloc(if size == 0 { 1 } else { size });
+    assert!(!ptr.is_null());
+    ptr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 16] = [
+        0x6e, 0x3f, 0x0b, 0x2c, 0x4f, 0x1d, 0x4c, 0x2a,
+        0x9b, 0x8e, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e, 0x6f,
+    ];
+
+    #[test]
+    fn uuid_with_hyphens() {
+        let uuid = Uuid::parse("6e3f0b2c-4f1d-4c2a-9b8e-1a2b3c4d5e6f");
+        assert_eq!(uuid, Some(Uuid(BYTES)));
+    }
+
+    #[test]
+    fn uuid_without_hyphens() {
+        let uuid = Uuid::parse("6E3F0B2C4F1D4C2A9B8E1A2B3C4D5E6F");
+        assert_eq!(uuid, Some(Uuid(BYTES)));
+    }
+
+    #[test]
+    fn uuid_bad() {
+        assert_eq!(Uuid::parse("6e3f0b2c-4f1d-4c2a-9b8e-1a2b3c4d5e6"), None);
+        assert_eq!(Uuid::parse("6e3f0b2c-4f1d-4c2a-9b8e-1a2b3c4d5e6f0"), None);
+        assert_eq!(Uuid::parse("6e3f0b2c-4f1d-4c2a-9b8e-1a2b3c4d5e6g"), None);
+        assert_eq!(Uuid::parse(""), None);
+    }
+}
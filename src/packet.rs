@@ -0,0 +1,173 @@
+use {Data, FrameType, Picture, Priority, Unit, UnitType};
+use std::vec::Vec;
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use core::result;
+
+/// An encoded frame that owns its data.
+///
+/// Unlike `Data`, this stays valid after the next call to the encoder, so it
+/// can be queued or sent to another thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawPacket"))]
+pub struct Packet {
+    data: Vec<u8>,
+    units: Vec<Boundary>,
+    pts: i64,
+    dts: i64,
+    keyframe: bool,
+    frame_type: FrameType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Boundary {
+    start: usize,
+    end: usize,
+    priority: Priority,
+    kind: UnitType,
+    first_mb: i32,
+    last_mb: i32,
+}
+
+// A packet whose units haven't been checked against its data yet.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawPacket {
+    data: Vec<u8>,
+    units: Vec<Boundary>,
+    pts: i64,
+    dts: i64,
+    keyframe: bool,
+    frame_type: FrameType,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawPacket> for Packet {
+    type Error = &'static str;
+
+    fn try_from(raw: RawPacket) -> result::Result<Self, Self::Error> {
+        let len = raw.data.len();
+
+        if raw.units.iter().any(|b| b.start > b.end || b.end > len) {
+            return Err("a unit lies outside the packet's data");
+        }
+
+        Ok(Packet {
+            data: raw.data,
+            units: raw.units,
+            pts: raw.pts,
+            dts: raw.dts,
+            keyframe: raw.keyframe,
+            frame_type: raw.frame_type,
+        })
+    }
+}
+
+impl Packet {
+    /// Copies the encoder's output.
+    ///
+    /// Returns `None` if there's no data, since then no frame came out, and
+    /// the picture means nothing.
+    pub fn new(data: &Data, picture: &Picture) -> Option<Self> {
+        if data.len() == 0 {
+            return None;
+        }
+
+        let entirety = data.entirety();
+        let base = entirety.as_ptr() as usize;
+
+        let units = (0..data.len())
+            .map(|i| {
+                let unit = data.unit(i);
+                let start = unit.as_ref().as_ptr() as usize - base;
+
+                Boundary {
+                    start,
+                    end: start + unit.as_ref().len(),
+                    priority: unit.priority(),
+                    kind: unit.kind(),
+                    first_mb: unit.first_mb(),
+                    last_mb: unit.last_mb(),
+                }
+            })
+            .collect();
+
+        Some(Packet {
+            data: entirety.to_vec(),
+            units,
+            pts: picture.pts(),
+            dts: picture.dts(),
+            keyframe: picture.keyframe(),
+            frame_type: picture.frame_type(),
+        })
+    }
+
+    /// The length (in NAL units, **not** in bytes) of this packet.
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    /// Whether there are no units.
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// The `i`th unit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out-of-bounds. In order to be within the bounds,
+    /// `i` must be less than `len`.
+    pub fn unit(&self, i: usize) -> Unit {
+        let b = &self.units[i];
+        let mbs = (b.first_mb, b.last_mb);
+        Unit::new(b.priority, b.kind, mbs, &self.data[b.start..b.end])
+    }
+
+    /// The index of the slice that the `i`th unit belongs to, counting from
+    /// zero within this packet, or `None` if the unit isn't a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out-of-bounds.
+    pub fn slice(&self, i: usize) -> Option<usize> {
+        if !self.units[i].kind.is_slice() {
+            return None;
+        }
+
+        Some(self.units[..i].iter().filter(|b| b.kind.is_slice()).count())
+    }
+
+    /// The entire chunk of data, as one big byte-slice.
+    pub fn entirety(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Takes the data out of the packet.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The presentation timestamp.
+    pub fn pts(&self) -> i64 {
+        self.pts
+    }
+
+    /// The decoding timestamp.
+    pub fn dts(&self) -> i64 {
+        self.dts
+    }
+
+    /// Whether the frame is a keyframe.
+    pub fn keyframe(&self) -> bool {
+        self.keyframe
+    }
+
+    /// How the frame was encoded.
+    pub fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+}
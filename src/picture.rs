@@ -2,7 +2,8 @@ use x264::*;
 
 /// Output information about an encoded frame.
 pub struct Picture {
-    raw: x264_picture_t
+    raw: x264_picture_t,
+    refreshed: bool,
 }
 
 impl Picture {
@@ -21,8 +22,76 @@ impl Picture {
         self.raw.i_dts
     }
 
+    /// How the frame was encoded.
+    pub fn frame_type(&self) -> FrameType {
+        FrameType::from_raw(self.raw.i_type)
+    }
+
+    /// Whether this frame finishes an intra refresh, so that a decoder that
+    /// started at the last keyframe can now show a clean picture.
+    ///
+    /// This is always true for IDR frames when intra refresh is turned on,
+    /// and always false when it's turned off.
+    pub fn refreshed(&self) -> bool {
+        self.refreshed
+    }
+
+    pub(crate) fn set_refreshed(&mut self) {
+        self.refreshed = true;
+    }
+
     #[doc(hidden)]
     pub unsafe fn from_raw(raw: x264_picture_t) -> Self {
-        Self { raw }
+        Self { raw, refreshed: false }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// The type of a frame.
+pub enum FrameType {
+    /// Whatever the encoder decides.
+    Auto = X264_TYPE_AUTO as i32,
+    /// An intra frame that no later frame references past.
+    Idr = X264_TYPE_IDR as i32,
+    /// An intra frame.
+    I = X264_TYPE_I as i32,
+    /// A frame predicted from earlier frames.
+    P = X264_TYPE_P as i32,
+    /// A bidirectionally predicted frame that other frames reference.
+    BRef = X264_TYPE_BREF as i32,
+    /// A bidirectionally predicted frame.
+    B = X264_TYPE_B as i32,
+    /// An IDR or I frame, depending on whether open GOP is enabled.
+    Keyframe = X264_TYPE_KEYFRAME as i32,
+}
+
+impl FrameType {
+    /// Whether the frame can be decoded by itself.
+    pub fn is_intra(self) -> bool {
+        match self {
+            FrameType::Idr | FrameType::I | FrameType::Keyframe => true,
+            _ => false,
+        }
+    }
+
+    fn from_raw(raw: i32) -> Self {
+        const IDR: i32 = FrameType::Idr as i32;
+        const I: i32 = FrameType::I as i32;
+        const P: i32 = FrameType::P as i32;
+        const BREF: i32 = FrameType::BRef as i32;
+        const B: i32 = FrameType::B as i32;
+        const KEYFRAME: i32 = FrameType::Keyframe as i32;
+
+        match raw {
+            IDR      => FrameType::Idr,
+            I        => FrameType::I,
+            P        => FrameType::P,
+            BREF     => FrameType::BRef,
+            B        => FrameType::B,
+            KEYFRAME => FrameType::Keyframe,
+            _        => FrameType::Auto,
+        }
     }
 }
@@ -0,0 +1,102 @@
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How motion vectors are searched for, from fastest to most thorough.
+pub enum MeMethod {
+    /// A diamond search with a radius of one.
+    Dia = X264_ME_DIA as i32,
+    /// A hexagon search with a radius of two.
+    Hex = X264_ME_HEX as i32,
+    /// An uneven multi-hexagon search.
+    Umh = X264_ME_UMH as i32,
+    /// An exhaustive search.
+    Esa = X264_ME_ESA as i32,
+    /// A transformed exhaustive search, which is even slower.
+    Tesa = X264_ME_TESA as i32,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(u32)]
+/// A macroblock partition that the encoder may consider in I-frames.
+pub enum IntraPartition {
+    /// 4x4 intra blocks.
+    I4x4 = X264_ANALYSE_I4x4 as u32,
+    /// 8x8 intra blocks, which need the 8x8 transform.
+    I8x8 = X264_ANALYSE_I8x8 as u32,
+}
+
+impl IntraPartition {
+    #[doc(hidden)]
+    pub fn to_flags(partitions: &[IntraPartition]) -> u32 {
+        partitions.iter().fold(0, |flags, &p| flags | p as u32)
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(u32)]
+/// A macroblock partition that the encoder may consider in P-frames and
+/// B-frames, which can also hold intra blocks.
+pub enum Partition {
+    /// 4x4 intra blocks.
+    I4x4 = X264_ANALYSE_I4x4 as u32,
+    /// 8x8 intra blocks, which need the 8x8 transform.
+    I8x8 = X264_ANALYSE_I8x8 as u32,
+    /// 16x8, 8x16 and 8x8 blocks in P-frames.
+    P8x8 = X264_ANALYSE_PSUB16x16 as u32,
+    /// 8x4, 4x8 and 4x4 blocks in P-frames.
+    P4x4 = X264_ANALYSE_PSUB8x8 as u32,
+    /// 16x8, 8x16 and 8x8 blocks in B-frames.
+    B8x8 = X264_ANALYSE_BSUB16x16 as u32,
+}
+
+impl Partition {
+    #[doc(hidden)]
+    pub fn to_flags(partitions: &[Partition]) -> u32 {
+        partitions.iter().fold(0, |flags, &p| flags | p as u32)
+    }
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// When to use trellis quantization.
+pub enum Trellis {
+    /// Never.
+    Off = 0,
+    /// Only on the final encode of each macroblock.
+    FinalOnly = 1,
+    /// On every mode decision, which is slower.
+    All = 2,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How motion vectors are predicted for direct blocks in B-frames.
+pub enum DirectPrediction {
+    /// Direct blocks aren't used.
+    None = X264_DIRECT_PRED_NONE as i32,
+    /// From neighbouring blocks.
+    Spatial = X264_DIRECT_PRED_SPATIAL as i32,
+    /// From the same block in the reference frames.
+    Temporal = X264_DIRECT_PRED_TEMPORAL as i32,
+    /// Whichever works better for each frame.
+    Auto = X264_DIRECT_PRED_AUTO as i32,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How P-frames handle fades, by weighting their references.
+pub enum WeightedPrediction {
+    /// References aren't weighted.
+    None = X264_WEIGHTP_NONE as i32,
+    /// References are weighted, but not duplicated.
+    Simple = X264_WEIGHTP_SIMPLE as i32,
+    /// References are weighted and duplicated, which handles fades best.
+    Smart = X264_WEIGHTP_SMART as i32,
+}
@@ -0,0 +1,269 @@
+use super::profile::high_depth;
+use {Encoding, Error, Profile, Result, Violation};
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// The class of AVC-Intra video, named for its bitrate in megabits.
+pub enum AvcIntra {
+    /// 4:2:0 at 10 bits, and a reduced width.
+    Class50,
+    /// 4:2:2 at 10 bits.
+    Class100,
+    /// 4:2:2 at 10 bits, with twice the bitrate.
+    Class200,
+}
+
+impl AvcIntra {
+    #[doc(hidden)]
+    pub fn class(self) -> i32 {
+        match self {
+            AvcIntra::Class50  => 50,
+            AvcIntra::Class100 => 100,
+            AvcIntra::Class200 => 200,
+        }
+    }
+}
+
+// A standard that limits the formats that can be encoded.
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(super) enum Compliance {
+    Bluray,
+    AvcIntra(AvcIntra),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scan {
+    Progressive,
+    Interlaced,
+    Either,
+}
+
+const FILM: (u32, u32) = (24000, 1001);
+const FILM_EXACT: (u32, u32) = (24, 1);
+const PAL: (u32, u32) = (25, 1);
+const NTSC: (u32, u32) = (30000, 1001);
+const PAL_DOUBLE: (u32, u32) = (50, 1);
+const NTSC_DOUBLE: (u32, u32) = (60000, 1001);
+
+// The width, height, framerate and scanning of each format.
+type Format = (i32, i32, (u32, u32), Scan);
+
+const BLURAY: &[Format] = &[
+    (1920, 1080, FILM,        Scan::Progressive),
+    (1920, 1080, FILM_EXACT,  Scan::Progressive),
+    (1920, 1080, PAL,         Scan::Interlaced),
+    (1920, 1080, NTSC,        Scan::Interlaced),
+    (1440, 1080, FILM,        Scan::Progressive),
+    (1440, 1080, FILM_EXACT,  Scan::Progressive),
+    (1440, 1080, PAL,         Scan::Interlaced),
+    (1440, 1080, NTSC,        Scan::Interlaced),
+    (1280,  720, FILM,        Scan::Progressive),
+    (1280,  720, FILM_EXACT,  Scan::Progressive),
+    (1280,  720, PAL_DOUBLE,  Scan::Progressive),
+    (1280,  720, NTSC_DOUBLE, Scan::Progressive),
+    ( 720,  480, NTSC,        Scan::Interlaced),
+    ( 720,  576, PAL,         Scan::Interlaced),
+];
+
+const AVC_INTRA_50: &[Format] = &[
+    (1440, 1080, FILM,        Scan::Progressive),
+    (1440, 1080, PAL,         Scan::Either),
+    (1440, 1080, NTSC,        Scan::Either),
+    ( 960,  720, PAL_DOUBLE,  Scan::Progressive),
+    ( 960,  720, NTSC_DOUBLE, Scan::Progressive),
+];
+
+const AVC_INTRA: &[Format] = &[
+    (1920, 1080, FILM,        Scan::Progressive),
+    (1920, 1080, PAL,         Scan::Either),
+    (1920, 1080, NTSC,        Scan::Either),
+    (1280,  720, PAL_DOUBLE,  Scan::Progressive),
+    (1280,  720, NTSC_DOUBLE, Scan::Progressive),
+];
+
+impl Compliance {
+    // Fails if the standard doesn't allow the format.
+    pub(super) fn check(
+        self,
+        raw: &x264_param_t,
+        csp: Encoding,
+    ) -> Result<()> {
+        let high_depth = high_depth(csp);
+
+        let (formats, csp_ok) = match self {
+            Compliance::Bluray =>
+                (BLURAY, Profile::High.supports(csp)),
+            Compliance::AvcIntra(AvcIntra::Class50) =>
+                (AVC_INTRA_50, Profile::High10.supports(csp) && high_depth),
+            Compliance::AvcIntra(_) =>
+                (AVC_INTRA, Profile::High422.supports(csp)
+                    && !Profile::High10.supports(csp) && high_depth),
+        };
+
+        if !csp_ok {
+            return Err(Error::Compliance(Violation::Colorspace));
+        }
+
+        let interlaced = raw.b_interlaced != 0 || raw.b_fake_interlaced != 0;
+        let fps = (raw.i_fps_num as u64, raw.i_fps_den as u64);
+
+        // Each step narrows down the formats, so that the error points at
+        // the first thing that doesn't fit.
+        let sized = || formats.iter().filter(|f| {
+            f.0 == raw.i_width && f.1 == raw.i_height
+        });
+
+        let timed = || sized().filter(|f| {
+            let (num, den) = f.2;
+            fps.1 != 0 && fps.0 * den as u64 == num as u64 * fps.1
+        });
+
+        let scanned = || timed().filter(|f| match f.3 {
+            Scan::Progressive => !interlaced,
+            Scan::Interlaced  => interlaced,
+            Scan::Either      => true,
+        });
+
+        if sized().next().is_none() {
+            Err(Error::Compliance(Violation::Size))
+        } else if timed().next().is_none() {
+            Err(Error::Compliance(Violation::Framerate))
+        } else if scanned().next().is_none() {
+            Err(Error::Compliance(Violation::Scan))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use {Colorspace, Modifier};
+
+    const I420: Colorspace = Colorspace::I420;
+    const I422: Colorspace = Colorspace::I422;
+
+    fn high(csp: Colorspace) -> Encoding {
+        Encoding::from(csp).add(Modifier::HighDepth)
+    }
+
+    fn check(
+        compliance: Compliance,
+        (width, height): (i32, i32),
+        (num, den): (u32, u32),
+        interlaced: bool,
+        csp: Encoding,
+    ) -> Result<()> {
+        let mut raw = unsafe {
+            let mut raw = mem::uninitialized();
+            x264_param_default(&mut raw);
+            raw
+        };
+
+        raw.i_width = width;
+        raw.i_height = height;
+        raw.i_fps_num = num;
+        raw.i_fps_den = den;
+        raw.b_interlaced = if interlaced { 1 } else { 0 };
+        compliance.check(&raw, csp)
+    }
+
+    fn violation(v: Violation) -> Result<()> {
+        Err(Error::Compliance(v))
+    }
+
+    #[test]
+    fn bluray() {
+        let c = Compliance::Bluray;
+        let csp = Encoding::from(I420);
+
+        assert_eq!(check(c, (1920, 1080), FILM, false, csp), Ok(()));
+        assert_eq!(check(c, (1440, 1080), NTSC, true, csp), Ok(()));
+        assert_eq!(check(c, (1280, 720), NTSC_DOUBLE, false, csp), Ok(()));
+
+        assert_eq!(check(c, (1920, 1088), FILM, false, csp),
+                   violation(Violation::Size));
+        assert_eq!(check(c, (1920, 1080), (30, 1), false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1920, 1080), (0, 0), false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1920, 1080), (24, 0), false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1920, 1080), PAL, false, csp),
+                   violation(Violation::Scan));
+        assert_eq!(check(c, (1280, 720), PAL_DOUBLE, true, csp),
+                   violation(Violation::Scan));
+        assert_eq!(check(c, (1920, 1080), FILM, false, high(I420)),
+                   violation(Violation::Colorspace));
+        assert_eq!(check(c, (1920, 1080), FILM, false, I422.into()),
+                   violation(Violation::Colorspace));
+    }
+
+    #[test]
+    fn avc_intra_50() {
+        let c = Compliance::AvcIntra(AvcIntra::Class50);
+        let csp = high(I420);
+
+        assert_eq!(check(c, (1440, 1080), PAL, true, csp), Ok(()));
+        assert_eq!(check(c, (1440, 1080), PAL, false, csp), Ok(()));
+        assert_eq!(check(c, (960, 720), PAL_DOUBLE, false, csp), Ok(()));
+
+        assert_eq!(check(c, (1920, 1080), PAL, true, csp),
+                   violation(Violation::Size));
+        assert_eq!(check(c, (1440, 1080), FILM_EXACT, false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1440, 1080), (25, 0), false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1440, 1080), FILM, true, csp),
+                   violation(Violation::Scan));
+        assert_eq!(check(c, (1440, 1080), PAL, true, I420.into()),
+                   violation(Violation::Colorspace));
+        assert_eq!(check(c, (1440, 1080), PAL, true, high(I422)),
+                   violation(Violation::Colorspace));
+    }
+
+    #[test]
+    fn avc_intra_100() {
+        let c = Compliance::AvcIntra(AvcIntra::Class100);
+        let csp = high(I422);
+
+        assert_eq!(check(c, (1920, 1080), NTSC, false, csp), Ok(()));
+        assert_eq!(check(c, (1920, 1080), NTSC, true, Colorspace::V210.into()),
+                   Ok(()));
+        assert_eq!(check(c, (1280, 720), PAL_DOUBLE, false, csp), Ok(()));
+
+        assert_eq!(check(c, (1440, 1080), PAL, true, csp),
+                   violation(Violation::Size));
+        assert_eq!(check(c, (1280, 720), PAL, false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1280, 720), (0, 0), false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1280, 720), NTSC_DOUBLE, true, csp),
+                   violation(Violation::Scan));
+        assert_eq!(check(c, (1920, 1080), PAL, true, I422.into()),
+                   violation(Violation::Colorspace));
+        assert_eq!(check(c, (1920, 1080), PAL, true, high(I420)),
+                   violation(Violation::Colorspace));
+    }
+
+    #[test]
+    fn avc_intra_200() {
+        let c = Compliance::AvcIntra(AvcIntra::Class200);
+        let csp = high(I422);
+
+        assert_eq!(check(c, (1920, 1080), FILM, false, csp), Ok(()));
+
+        assert_eq!(check(c, (960, 720), PAL_DOUBLE, false, csp),
+                   violation(Violation::Size));
+        assert_eq!(check(c, (1920, 1080), NTSC_DOUBLE, false, csp),
+                   violation(Violation::Framerate));
+        assert_eq!(check(c, (1920, 1080), FILM, true, csp),
+                   violation(Violation::Scan));
+        assert_eq!(check(c, (1920, 1080), FILM, false, high(I420)),
+                   violation(Violation::Colorspace));
+    }
+}
@@ -0,0 +1,307 @@
+use {Error, Result};
+use x264::*;
+
+const JVT_4I: [u8; 16] = [
+     6, 13, 20, 28,
+    13, 20, 28, 32,
+    20, 28, 32, 37,
+    28, 32, 37, 42,
+];
+
+const JVT_4P: [u8; 16] = [
+    10, 14, 20, 24,
+    14, 20, 24, 27,
+    20, 24, 27, 30,
+    24, 27, 30, 34,
+];
+
+const JVT_8I: [u8; 64] = [
+     6, 10, 13, 16, 18, 23, 25, 27,
+    10, 11, 16, 18, 23, 25, 27, 29,
+    13, 16, 18, 23, 25, 27, 29, 31,
+    16, 18, 23, 25, 27, 29, 31, 33,
+    18, 23, 25, 27, 29, 31, 33, 36,
+    23, 25, 27, 29, 31, 33, 36, 38,
+    25, 27, 29, 31, 33, 36, 38, 40,
+    27, 29, 31, 33, 36, 38, 40, 42,
+];
+
+const JVT_8P: [u8; 64] = [
+     9, 13, 15, 17, 19, 21, 22, 24,
+    13, 13, 17, 19, 21, 22, 24, 25,
+    15, 17, 19, 21, 22, 24, 25, 27,
+    17, 19, 21, 22, 24, 25, 27, 28,
+    19, 21, 22, 24, 25, 27, 28, 30,
+    21, 22, 24, 25, 27, 28, 30, 32,
+    22, 24, 25, 27, 28, 30, 32, 33,
+    24, 25, 27, 28, 30, 32, 33, 35,
+];
+
+#[derive(Clone, Copy)]
+/// The quantization matrices, which weigh how much detail is kept at each
+/// frequency.
+pub enum Cqm {
+    /// Every frequency is treated the same.
+    Flat,
+    /// The defaults from the standard, which keep more low frequencies.
+    Jvt,
+    /// Some other matrices.
+    Custom(Matrices),
+}
+
+/// A full set of custom quantization matrices, in raster order.
+///
+/// The 8x8 chroma matrices are only used for 4:4:4 video.
+#[derive(Clone, Copy)]
+pub struct Matrices {
+    /// For 4x4 intra luma blocks.
+    pub intra4_luma: [u8; 16],
+    /// For 4x4 inter luma blocks.
+    pub inter4_luma: [u8; 16],
+    /// For 4x4 intra chroma blocks.
+    pub intra4_chroma: [u8; 16],
+    /// For 4x4 inter chroma blocks.
+    pub inter4_chroma: [u8; 16],
+    /// For 8x8 intra luma blocks.
+    pub intra8_luma: [u8; 64],
+    /// For 8x8 inter luma blocks.
+    pub inter8_luma: [u8; 64],
+    /// For 8x8 intra chroma blocks.
+    pub intra8_chroma: [u8; 64],
+    /// For 8x8 inter chroma blocks.
+    pub inter8_chroma: [u8; 64],
+}
+
+impl Matrices {
+    /// Every entry is 16, which is the same as no matrices at all.
+    pub fn flat() -> Self {
+        Matrices {
+            intra4_luma: [16; 16],
+            inter4_luma: [16; 16],
+            intra4_chroma: [16; 16],
+            inter4_chroma: [16; 16],
+            intra8_luma: [16; 64],
+            inter8_luma: [16; 64],
+            intra8_chroma: [16; 64],
+            inter8_chroma: [16; 64],
+        }
+    }
+
+    /// The defaults from the standard.
+    pub fn jvt() -> Self {
+        Matrices {
+            intra4_luma: JVT_4I,
+            inter4_luma: JVT_4P,
+            intra4_chroma: JVT_4I,
+            inter4_chroma: JVT_4P,
+            intra8_luma: JVT_8I,
+            inter8_luma: JVT_8P,
+            intra8_chroma: JVT_8I,
+            inter8_chroma: JVT_8P,
+        }
+    }
+
+    /// Parses a file in the JM format that x264's `--cqmfile` accepts.
+    ///
+    /// Each matrix is a name like `INTRA4X4_LUMA` followed by its entries,
+    /// and anything after a `#` is a comment. Like in x264, a missing
+    /// matrix is flat, one that starts with a zero is the default, and
+    /// only the first of the `U` and `V` chroma matrices is used.
+    ///
+    /// ```rust
+    /// # use x264::Matrices;
+    /// let matrices = Matrices::parse("
+    ///     # Only the 4x4 intra luma matrix is custom.
+    ///     INTRA4X4_LUMA =
+    ///     6,13,20,28,
+    ///     13,20,28,32,
+    ///     20,28,32,37,
+    ///     28,32,37,42
+    ///     INTER4X4_LUMA = 0
+    /// ").unwrap();
+    /// ```
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut matrices = Self::flat();
+        // The matrix being filled, and how many entries it has so far.
+        let mut current: Option<(&mut [u8], &[u8], usize)> = None;
+        let mut seen = 0u8;
+        let mut ignored = [0; 64];
+
+        let words = s.lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split(|c: char| {
+                c.is_whitespace() || c == ',' || c == '='
+            }))
+            .filter(|word| !word.is_empty());
+
+        for word in words {
+            if let Ok(n) = word.parse::<u32>() {
+                let (matrix, jvt, len) = match current {
+                    Some((ref mut matrix, jvt, ref mut len)) =>
+                        (&mut **matrix, jvt, len),
+                    None => return Err(Error::BadValue),
+                };
+
+                if *len == 0 && n == 0 {
+                    matrix.copy_from_slice(jvt);
+                    *len = matrix.len();
+                    continue;
+                }
+
+                if n < 1 || n > 255 || *len == matrix.len() {
+                    return Err(Error::BadValue);
+                }
+
+                matrix[*len] = n as u8;
+                *len += 1;
+                continue;
+            }
+
+            if let Some((ref matrix, _, len)) = current {
+                if len != matrix.len() {
+                    return Err(Error::BadValue);
+                }
+            }
+
+            let (bit, matrix, jvt): (_, &mut [u8], &[u8]) = match word {
+                "INTRA4X4_LUMA" =>
+                    (0, &mut matrices.intra4_luma, &JVT_4I),
+                "INTER4X4_LUMA" =>
+                    (1, &mut matrices.inter4_luma, &JVT_4P),
+                "INTRA4X4_CHROMA" | "INTRA4X4_CHROMAU" |
+                "INTRA4X4_CHROMAV" =>
+                    (2, &mut matrices.intra4_chroma, &JVT_4I),
+                "INTER4X4_CHROMA" | "INTER4X4_CHROMAU" |
+                "INTER4X4_CHROMAV" =>
+                    (3, &mut matrices.inter4_chroma, &JVT_4P),
+                "INTRA8X8_LUMA" =>
+                    (4, &mut matrices.intra8_luma, &JVT_8I),
+                "INTER8X8_LUMA" =>
+                    (5, &mut matrices.inter8_luma, &JVT_8P),
+                "INTRA8X8_CHROMA" | "INTRA8X8_CHROMAU" |
+                "INTRA8X8_CHROMAV" =>
+                    (6, &mut matrices.intra8_chroma, &JVT_8I),
+                "INTER8X8_CHROMA" | "INTER8X8_CHROMAU" |
+                "INTER8X8_CHROMAV" =>
+                    (7, &mut matrices.inter8_chroma, &JVT_8P),
+                _ => return Err(Error::BadValue),
+            };
+
+            // Later copies of a matrix are read, but thrown away.
+            current = if seen & 1 << bit == 0 {
+                seen |= 1 << bit;
+                Some((matrix, jvt, 0))
+            } else {
+                Some((&mut ignored[..matrix.len()], jvt, 0))
+            };
+        }
+
+        if let Some((ref matrix, _, len)) = current {
+            if len != matrix.len() {
+                return Err(Error::BadValue);
+            }
+        }
+
+        Ok(matrices)
+    }
+
+    #[doc(hidden)]
+    pub fn apply(&self, raw: &mut x264_param_t) {
+        raw.cqm_4iy = self.intra4_luma;
+        raw.cqm_4py = self.inter4_luma;
+        raw.cqm_4ic = self.intra4_chroma;
+        raw.cqm_4pc = self.inter4_chroma;
+        raw.cqm_8iy = self.intra8_luma;
+        raw.cqm_8py = self.inter8_luma;
+        raw.cqm_8ic = self.intra8_chroma;
+        raw.cqm_8pc = self.inter8_chroma;
+    }
+}
+
+impl Cqm {
+    #[doc(hidden)]
+    pub fn apply(&self, raw: &mut x264_param_t) {
+        raw.i_cqm_preset = match *self {
+            Cqm::Flat => X264_CQM_FLAT as i32,
+            Cqm::Jvt => X264_CQM_JVT as i32,
+            Cqm::Custom(ref matrices) => {
+                matrices.apply(raw);
+                X264_CQM_CUSTOM as i32
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    const CUSTOM: &str = "
+        # A comment, with INTER4X4_LUMA in it.
+        INTRA4X4_LUMA =
+        1,2,3,4,
+        5,6,7,8,
+        9,10,11,12,
+        13,14,15,16
+    ";
+
+    #[test]
+    fn custom() {
+        let matrices = Matrices::parse(CUSTOM).unwrap();
+        let expected: Vec<u8> = (1..17).collect();
+        assert_eq!(&matrices.intra4_luma[..], &expected[..]);
+        assert_eq!(matrices.inter4_luma, [16; 16]);
+        assert_eq!(&matrices.intra8_luma[..], &[16; 64][..]);
+    }
+
+    #[test]
+    fn leading_zero_is_jvt() {
+        let matrices = Matrices::parse("
+            INTRA4X4_LUMA = 0
+            INTER8X8_LUMA = 0
+        ").unwrap();
+        assert_eq!(matrices.intra4_luma, JVT_4I);
+        assert_eq!(&matrices.inter8_luma[..], &JVT_8P[..]);
+        assert_eq!(matrices.inter4_luma, [16; 16]);
+    }
+
+    #[test]
+    fn too_few() {
+        assert!(Matrices::parse("INTRA4X4_LUMA = 1,2,3").is_err());
+        assert!(Matrices::parse("
+            INTRA4X4_LUMA = 1,2,3
+            INTER4X4_LUMA = 0
+        ").is_err());
+    }
+
+    #[test]
+    fn too_many() {
+        let extra = format!("{}, 17", CUSTOM);
+        assert!(Matrices::parse(&extra).is_err());
+        assert!(Matrices::parse("INTRA4X4_LUMA = 0, 1").is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let text = CUSTOM.replace("16", "256");
+        assert!(Matrices::parse(&text).is_err());
+    }
+
+    #[test]
+    fn unknown_name() {
+        assert!(Matrices::parse("INTRA16X16_LUMA = 0").is_err());
+        assert!(Matrices::parse("16").is_err());
+    }
+
+    #[test]
+    fn first_chroma_wins() {
+        let text = CUSTOM.replace("INTRA4X4_LUMA", "INTRA4X4_CHROMAU")
+            + &CUSTOM.replace("INTRA4X4_LUMA", "INTRA4X4_CHROMAV")
+                .replace("1,2", "20,30");
+        let matrices = Matrices::parse(&text).unwrap();
+        let expected: Vec<u8> = (1..17).collect();
+        assert_eq!(&matrices.intra4_chroma[..], &expected[..]);
+        assert_eq!(matrices.intra4_luma, [16; 16]);
+    }
+}
@@ -0,0 +1,172 @@
+use {Encoding, Profile};
+use core::cmp;
+use x264::*;
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An H.264 level, which limits the decoder resources needed by the stream.
+pub enum Level {
+    L1,
+    L1b,
+    L1_1,
+    L1_2,
+    L1_3,
+    L2,
+    L2_1,
+    L2_2,
+    L3,
+    L3_1,
+    L3_2,
+    L4,
+    L4_1,
+    L4_2,
+    L5,
+    L5_1,
+    L5_2,
+    L6,
+    L6_1,
+    L6_2,
+}
+
+// Table A-1 of the H.264 specification, in the same order as `Level`.
+//
+// (level_idc, MaxMBPS, MaxFS, MaxDpbMbs, MaxBR, MaxCPB)
+const LIMITS: [(i32, u64, u64, u64, u64, u64); 20] = [
+    (10,     1485,     99,    396,     64,    175),
+    ( 9,     1485,     99,    396,    128,    350),
+    (11,     3000,    396,    900,    192,    500),
+    (12,     6000,    396,   2376,    384,   1000),
+    (13,    11880,    396,   2376,    768,   2000),
+    (20,    11880,    396,   2376,   2000,   2000),
+    (21,    19800,    792,   4752,   4000,   4000),
+    (22,    20250,   1620,   8100,   4000,   4000),
+    (30,    40500,   1620,   8100,  10000,  10000),
+    (31,   108000,   3600,  18000,  14000,  14000),
+    (32,   216000,   5120,  20480,  20000,  20000),
+    (40,   245760,   8192,  32768,  20000,  25000),
+    (41,   245760,   8192,  32768,  50000,  62500),
+    (42,   522240,   8704,  34816,  50000,  62500),
+    (50,   589824,  22080, 110400, 135000, 135000),
+    (51,   983040,  36864, 184320, 240000, 240000),
+    (52,  2073600,  36864, 184320, 240000, 240000),
+    (60,  4177920, 139264, 696320, 240000, 240000),
+    (61,  8355840, 139264, 696320, 480000, 480000),
+    (62, 16711680, 139264, 696320, 800000, 800000),
+];
+
+impl Level {
+    /// Every level, from the lowest to the highest.
+    pub const ALL: [Level; 20] = [
+        Level::L1,   Level::L1b,  Level::L1_1, Level::L1_2, Level::L1_3,
+        Level::L2,   Level::L2_1, Level::L2_2, Level::L3,   Level::L3_1,
+        Level::L3_2, Level::L4,   Level::L4_1, Level::L4_2, Level::L5,
+        Level::L5_1, Level::L5_2, Level::L6,   Level::L6_1, Level::L6_2,
+    ];
+
+    /// The `level_idc` that x264 uses for this level.
+    ///
+    /// Level 1b is represented as 9, like x264 does.
+    pub fn idc(self) -> i32 {
+        LIMITS[self as usize].0
+    }
+
+    /// Whether a stream with the given demands can be decoded at this level.
+    pub(super) fn allows(self, demand: &Demand) -> bool {
+        let (_, mbps, fs, dpb, br, cpb) = LIMITS[self as usize];
+
+        // The bitrate limits are scaled up for the higher profiles.
+        let (br, cpb) = (br * demand.factor / 4, cpb * demand.factor / 4);
+
+        demand.fs <= fs
+            && demand.mb_width * demand.mb_width <= 8 * fs
+            && demand.mb_height * demand.mb_height <= 8 * fs
+            && demand.mbps <= mbps
+            && demand.fs * demand.refs <= dpb
+            && demand.br <= br
+            && demand.cpb <= cpb
+    }
+
+    /// The lowest level that allows a stream with the given demands.
+    pub(super) fn minimum(demand: &Demand) -> Option<Level> {
+        Level::ALL.iter().cloned().find(|level| level.allows(demand))
+    }
+}
+
+/// The decoder resources needed by a stream.
+pub(super) struct Demand {
+    mb_width: u64,
+    mb_height: u64,
+    fs: u64,
+    mbps: u64,
+    refs: u64,
+    br: u64,
+    cpb: u64,
+    factor: u64,
+}
+
+impl Demand {
+    pub(super) fn from_raw(raw: &x264_param_t, profile: Option<Profile>) -> Self {
+        let mb_width  = (cmp::max(raw.i_width, 0) as u64 + 15) / 16;
+        let mb_height = (cmp::max(raw.i_height, 0) as u64 + 15) / 16;
+        let fs        = mb_width * mb_height;
+
+        let mbps = if raw.i_fps_den == 0 {
+            0
+        } else {
+            let num = fs * raw.i_fps_num as u64;
+            let den = raw.i_fps_den as u64;
+            (num + den - 1) / den
+        };
+
+        // This mirrors how x264 sizes the decoded picture buffer.
+        let pyramid = raw.i_bframe > 1 && raw.i_bframe_pyramid != 0;
+        let reorder = if pyramid { 2 } else if raw.i_bframe > 0 { 1 } else { 0 };
+        let refs = cmp::max(
+            cmp::max(raw.i_frame_reference, 1 + reorder),
+            cmp::max(if pyramid { 4 } else { 1 }, raw.i_dpb_size),
+        );
+        let refs = cmp::min(refs, 16) as u64;
+
+        let factor = match profile.unwrap_or_else(|| infer_profile(raw)) {
+            Profile::High              => 5,
+            Profile::High10            => 12,
+            Profile::High422           => 16,
+            Profile::High444Predictive => 16,
+            _                          => 4,
+        };
+
+        Demand {
+            mb_width,
+            mb_height,
+            fs,
+            mbps,
+            refs,
+            br: cmp::max(raw.rc.i_vbv_max_bitrate, 0) as u64,
+            cpb: cmp::max(raw.rc.i_vbv_buffer_size, 0) as u64,
+            factor,
+        }
+    }
+}
+
+// The profile that x264 picks by itself when none is asked for, which only
+// tells apart what the bitrate limits do.
+fn infer_profile(raw: &x264_param_t) -> Profile {
+    let encoding = unsafe { Encoding::from_raw(raw.i_csp) };
+    let lossless = raw.rc.i_rc_method == X264_RC_CQP as i32
+                && raw.rc.i_qp_constant == 0;
+
+    if lossless || !Profile::High422.supports(encoding) {
+        Profile::High444Predictive
+    } else if !Profile::High10.supports(encoding) {
+        Profile::High422
+    } else if !Profile::High.supports(encoding) {
+        Profile::High10
+    } else if raw.analyse.b_transform_8x8 != 0
+           || raw.i_cqm_preset != X264_CQM_FLAT as i32
+    {
+        Profile::High
+    } else {
+        Profile::Main
+    }
+}
@@ -0,0 +1,125 @@
+use x264::*;
+#[cfg(feature = "std")]
+use core::cmp;
+#[cfg(feature = "std")]
+use core::ffi::c_void;
+#[cfg(feature = "std")]
+use std::boxed::Box;
+#[cfg(feature = "std")]
+use std::os::raw::c_char;
+#[cfg(feature = "std")]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "std")]
+use std::string::String;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How much x264 should say about what it's doing.
+pub enum LogLevel {
+    /// Nothing at all.
+    None = X264_LOG_NONE as i32,
+    /// Only errors.
+    Error = X264_LOG_ERROR as i32,
+    /// Errors and warnings.
+    Warning = X264_LOG_WARNING as i32,
+    /// Errors, warnings and statistics.
+    Info = X264_LOG_INFO as i32,
+    /// Everything.
+    Debug = X264_LOG_DEBUG as i32,
+}
+
+impl LogLevel {
+    #[cfg(feature = "std")]
+    fn from_raw(raw: i32) -> Self {
+        const ERROR: i32 = LogLevel::Error as i32;
+        const WARNING: i32 = LogLevel::Warning as i32;
+        const INFO: i32 = LogLevel::Info as i32;
+
+        match raw {
+            ERROR   => LogLevel::Error,
+            WARNING => LogLevel::Warning,
+            INFO    => LogLevel::Info,
+            _       => LogLevel::Debug,
+        }
+    }
+}
+
+/// A function that receives x264's log messages.
+#[cfg(feature = "std")]
+pub type Logger = Box<dyn Fn(LogLevel, &str) + Send + Sync>;
+
+// bindgen turns `va_list` into a pointer where it's an array, which is only
+// the case with the x86-64 System V ABI, and leaves the alias alone elsewhere.
+#[cfg(all(feature = "std", target_arch = "x86_64", not(windows)))]
+type VaList = *mut __va_list_tag;
+#[cfg(all(feature = "std", not(all(target_arch = "x86_64", not(windows)))))]
+type VaList = va_list;
+
+#[cfg(feature = "std")]
+extern "C" {
+    fn vsnprintf(
+        s: *mut c_char,
+        n: usize,
+        format: *const c_char,
+        args: VaList,
+    ) -> i32;
+}
+
+#[cfg(feature = "std")]
+pub unsafe extern "C" fn log_callback(
+    private: *mut c_void,
+    level: i32,
+    format: *const c_char,
+    args: VaList,
+) {
+    let logger = &*(private as *const Logger);
+
+    let mut buf = [0u8; 1024];
+    let ptr = buf.as_mut_ptr() as *mut c_char;
+    let len = vsnprintf(ptr, buf.len(), format, args);
+
+    if len < 0 {
+        return;
+    }
+
+    let len = cmp::min(len as usize, buf.len() - 1);
+    let message = String::from_utf8_lossy(&buf[..len]);
+    let level = LogLevel::from_raw(level);
+
+    // Unwinding into C is undefined behaviour.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        logger(level, message.trim_end())
+    }));
+}
+
+/// Sends a message from x264 to the `log` crate, under the `x264` target.
+#[cfg(feature = "log")]
+pub fn log_to_crate(level: LogLevel, message: &str) {
+    use log::Level;
+
+    let level = match level {
+        LogLevel::None    => return,
+        LogLevel::Error   => Level::Error,
+        LogLevel::Warning => Level::Warn,
+        LogLevel::Info    => Level::Info,
+        LogLevel::Debug   => Level::Debug,
+    };
+
+    log!(target: "x264", level, "{}", message);
+}
+
+/// The x264 level that lets through whatever the `log` crate would record.
+#[cfg(feature = "log")]
+pub fn log_to_crate_level() -> LogLevel {
+    use log::LevelFilter;
+
+    match ::log::max_level() {
+        LevelFilter::Off   => LogLevel::None,
+        LevelFilter::Error => LogLevel::Error,
+        LevelFilter::Warn  => LogLevel::Warning,
+        LevelFilter::Info  => LogLevel::Info,
+        LevelFilter::Debug => LogLevel::Debug,
+        LevelFilter::Trace => LogLevel::Debug,
+    }
+}
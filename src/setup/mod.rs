@@ -1,16 +1,83 @@
+use encoder::Owned;
+use image;
 use {Encoder, Encoding, Error, Result};
-use core::mem;
+#[cfg(feature = "std")]
+use Unit;
+use core::{cmp, mem};
+#[cfg(feature = "std")]
+use core::ffi::c_void;
+#[cfg(feature = "std")]
+use core::ptr;
+#[cfg(feature = "std")]
+use std::boxed::Box;
+#[cfg(feature = "std")]
+use std::ffi::CString;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+use self::compliance::Compliance;
+use self::level::Demand;
 use x264::*;
 
+mod analysis;
+mod compliance;
+mod cqm;
+mod level;
+mod logging;
+#[cfg(feature = "std")]
+mod nalu;
+mod packing;
 mod preset;
+mod profile;
+mod quality;
+#[cfg(feature = "serde")]
+mod settings;
 mod tune;
+mod vui;
+#[cfg(feature = "std")]
+mod zone;
 
+pub use self::analysis::*;
+pub use self::compliance::AvcIntra;
+pub use self::cqm::{Cqm, Matrices};
+pub use self::level::Level;
+pub use self::logging::LogLevel;
+#[cfg(feature = "std")]
+pub use self::logging::Logger;
+#[cfg(feature = "std")]
+pub use self::nalu::UnitCallback;
+#[cfg(feature = "std")]
+pub(crate) use self::nalu::UnitSink;
+pub use self::packing::FramePacking;
 pub use self::preset::*;
+pub use self::profile::*;
+pub use self::quality::*;
 pub use self::tune::*;
+pub use self::vui::*;
+#[cfg(feature = "std")]
+pub use self::zone::Zone;
 
 /// Builds a new encoder.
+///
+/// With the `serde` feature, this can be saved and reloaded to reproduce the
+/// exact same settings, as long as the same version of x264 is used.
+///
+/// Callbacks can't be saved, so the logger and unit callback are left out.
+/// Neither can the file names and strings given through `option`, like
+/// `zones` or `cqmfile`, so saving fails if any were given. Use `zones` and
+/// `cqm` instead, which are saved.
 pub struct Setup {
     raw: x264_param_t,
+    profile: Option<Profile>,
+    level: Option<Level>,
+    cabac: Option<bool>,
+    display_aspect: Option<(i32, i32)>,
+    compliance: Option<Compliance>,
+    #[cfg(feature = "std")]
+    logger: Option<Box<Logger>>,
+    #[cfg(feature = "std")]
+    on_unit: Option<Box<UnitCallback>>,
+    #[cfg(feature = "std")]
+    zones: Vec<Zone>,
 }
 
 impl Setup {
@@ -32,7 +99,68 @@ impl Setup {
             )
         });
 
-        Self { raw }
+        Self::from_raw(raw)
+    }
+
+    /// Creates a new builder for Blu-ray compatible video, with the
+    /// specified preset and tune.
+    ///
+    /// This uses the high profile at level 4.1, with the VBV and GOP limits
+    /// that players expect. Blu-ray only allows a few sizes and framerates,
+    /// and `build` fails on any others.
+    ///
+    /// ```rust
+    /// # use x264::{Preset, Setup, Tune};
+    /// let setup = Setup::bluray(Preset::Slow, Tune::Film)
+    ///     .fps(24000, 1001)
+    ///     .bitrate(25000);
+    /// ```
+    pub fn bluray(preset: Preset, tune: Tune) -> Self {
+        let mut setup = Self::preset(preset, tune, false, false)
+            .high()
+            .level(Level::L4_1);
+
+        let raw = &mut setup.raw;
+        raw.b_bluray_compat = 1;
+        raw.i_nal_hrd = X264_NAL_HRD_VBR as i32;
+        raw.rc.i_vbv_max_bitrate = 40000;
+        raw.rc.i_vbv_buffer_size = 30000;
+        raw.b_aud = 1;
+        raw.i_slice_count = 4;
+        raw.i_bframe = cmp::min(raw.i_bframe, 3);
+        raw.i_frame_reference = cmp::min(raw.i_frame_reference, 4);
+
+        setup.compliance = Some(Compliance::Bluray);
+        setup
+    }
+
+    /// Creates a new builder for AVC-Intra video of the given class.
+    ///
+    /// x264 picks almost every setting by itself. Each class only allows
+    /// a few sizes, framerates and colorspaces, and `build` fails on any
+    /// others.
+    pub fn avc_intra(class: AvcIntra) -> Self {
+        let mut setup = Self::default();
+        setup.raw.i_avcintra_class = class.class();
+        setup.compliance = Some(Compliance::AvcIntra(class));
+        setup
+    }
+
+    fn from_raw(raw: x264_param_t) -> Self {
+        Self {
+            raw,
+            profile: None,
+            level: None,
+            cabac: None,
+            display_aspect: None,
+            compliance: None,
+            #[cfg(feature = "std")]
+            logger: None,
+            #[cfg(feature = "std")]
+            on_unit: None,
+            #[cfg(feature = "std")]
+            zones: Vec::new(),
+        }
     }
 
     /// Makes the first pass faster.
@@ -73,40 +201,509 @@ impl Setup {
         self
     }
 
-    /// The lowest profile, with guaranteed compatibility with all decoders.
-    pub fn baseline(mut self) -> Self {
-        unsafe {
-            x264_param_apply_profile(
-                &mut self.raw,
-                b"baseline\0" as *const u8 as *const i8
-            );
-        }
+    /// How adaptive quantization spreads bits across each frame.
+    pub fn aq_mode(mut self, mode: AqMode) -> Self {
+        self.raw.rc.i_aq_mode = mode as i32;
         self
     }
 
-    /// A useless middleground between the baseline and high profiles.
-    pub fn main(mut self) -> Self {
-        unsafe {
-            x264_param_apply_profile(
+    /// How strongly adaptive quantization favours flat areas.
+    ///
+    /// Lower values suit screen content and animation, higher values suit
+    /// grainy film. The default is 1.
+    pub fn aq_strength(mut self, strength: f32) -> Self {
+        self.raw.rc.f_aq_strength = strength;
+        self
+    }
+
+    /// Whether to use psychovisual optimizations at all, which make the
+    /// video look better at the cost of metrics like PSNR and SSIM.
+    pub fn psy(mut self, psy: bool) -> Self {
+        self.raw.analyse.b_psy = if psy { 1 } else { 0 };
+        self
+    }
+
+    /// How strongly to preserve detail and energy, over exact fidelity.
+    ///
+    /// This needs psychovisual optimizations and a high enough subpixel
+    /// refinement. The default is 1.
+    pub fn psy_rd(mut self, strength: f32) -> Self {
+        self.raw.analyse.f_psy_rd = strength;
+        self
+    }
+
+    /// How strongly trellis quantization preserves grain and texture.
+    ///
+    /// This needs psychovisual optimizations and trellis quantization. The
+    /// default is 0.
+    pub fn psy_trellis(mut self, strength: f32) -> Self {
+        self.raw.analyse.f_psy_trellis = strength;
+        self
+    }
+
+    /// Whether to give more bits to the blocks that later frames reference.
+    pub fn mb_tree(mut self, mb_tree: bool) -> Self {
+        self.raw.rc.b_mb_tree = if mb_tree { 1 } else { 0 };
+        self
+    }
+
+    /// How motion vectors are searched for.
+    pub fn me_method(mut self, method: MeMethod) -> Self {
+        self.raw.analyse.i_me_method = method as i32;
+        self
+    }
+
+    /// How far, in pixels, the motion search looks.
+    ///
+    /// This only matters for the `Umh`, `Esa` and `Tesa` methods.
+    pub fn me_range(mut self, range: i32) -> Self {
+        self.raw.analyse.i_me_range = range;
+        self
+    }
+
+    /// How thoroughly to refine motion vectors and mode decisions, from 0
+    /// (fastest) to 11 (slowest).
+    pub fn subpel_refine(mut self, level: i32) -> Self {
+        self.raw.analyse.i_subpel_refine = level;
+        self
+    }
+
+    /// Which partitions are considered in I-frames.
+    pub fn intra_partitions(
+        mut self,
+        partitions: &[IntraPartition],
+    ) -> Self {
+        self.raw.analyse.intra = IntraPartition::to_flags(partitions);
+        self
+    }
+
+    /// Which partitions are considered in P-frames and B-frames.
+    pub fn inter_partitions(mut self, partitions: &[Partition]) -> Self {
+        self.raw.analyse.inter = Partition::to_flags(partitions);
+        self
+    }
+
+    /// Whether to allow the 8x8 transform, which needs the high profile.
+    pub fn transform_8x8(mut self, enabled: bool) -> Self {
+        self.raw.analyse.b_transform_8x8 = if enabled { 1 } else { 0 };
+        self
+    }
+
+    /// When to use trellis quantization.
+    pub fn trellis(mut self, trellis: Trellis) -> Self {
+        self.raw.analyse.i_trellis = trellis as i32;
+        self
+    }
+
+    /// How motion vectors are predicted for direct blocks in B-frames.
+    pub fn direct_prediction(mut self, prediction: DirectPrediction) -> Self {
+        self.raw.analyse.i_direct_mv_pred = prediction as i32;
+        self
+    }
+
+    /// How P-frames handle fades.
+    pub fn weighted_prediction(
+        mut self,
+        prediction: WeightedPrediction,
+    ) -> Self {
+        self.raw.analyse.i_weighted_pred = prediction as i32;
+        self
+    }
+
+    /// Whether each partition of a macroblock may use its own reference.
+    pub fn mixed_references(mut self, mixed: bool) -> Self {
+        self.raw.analyse.b_mixed_references = if mixed { 1 } else { 0 };
+        self
+    }
+
+    /// The shape of each pixel, as a ratio of its width to its height.
+    pub fn sar(mut self, width: i32, height: i32) -> Self {
+        self.raw.vui.i_sar_width = width;
+        self.raw.vui.i_sar_height = height;
+        self.display_aspect = None;
+        self
+    }
+
+    /// The shape that the (cropped) video should be shown at, like 16:9.
+    ///
+    /// The pixel shape that gets there is worked out by `build`, from the
+    /// size of the video.
+    ///
+    /// ```rust
+    /// # use x264::Setup;
+    /// // Anamorphic widescreen DVD video, which is 720x480.
+    /// let setup = Setup::default().display_aspect(16, 9);
+    /// ```
+    pub fn display_aspect(mut self, width: i32, height: i32) -> Self {
+        self.display_aspect = Some((width, height));
+        self
+    }
+
+    /// How many pixels to hide from each edge of the video.
+    pub fn crop(
+        mut self,
+        left: u32,
+        top: u32,
+        right: u32,
+        bottom: u32,
+    ) -> Self {
+        self.raw.crop_rect.i_left = left;
+        self.raw.crop_rect.i_top = top;
+        self.raw.crop_rect.i_right = right;
+        self.raw.crop_rect.i_bottom = bottom;
+        self
+    }
+
+    /// Whether the edges of the video may be hidden by the display.
+    pub fn overscan(mut self, overscan: Overscan) -> Self {
+        self.raw.vui.i_overscan = overscan as i32;
+        self
+    }
+
+    /// The analog format that the video came from.
+    pub fn video_format(mut self, format: VideoFormat) -> Self {
+        self.raw.vui.i_vidformat = format as i32;
+        self
+    }
+
+    /// Encodes the video as two interlaced fields per frame.
+    ///
+    /// The height must then split evenly into fields, or `build` fails.
+    pub fn interlaced(mut self, top_field_first: bool) -> Self {
+        self.raw.b_interlaced = 1;
+        self.raw.b_tff = if top_field_first { 1 } else { 0 };
+        self
+    }
+
+    /// Encodes progressive video, but flags it as interlaced, for decoders
+    /// that only accept interlaced video.
+    ///
+    /// Like with `interlaced`, the height must split evenly into fields.
+    pub fn fake_interlaced(mut self, top_field_first: bool) -> Self {
+        self.raw.b_fake_interlaced = 1;
+        self.raw.b_tff = if top_field_first { 1 } else { 0 };
+        self
+    }
+
+    /// Encodes the video as whole frames, undoing `interlaced` and
+    /// `fake_interlaced`, which is the default.
+    pub fn progressive(mut self) -> Self {
+        self.raw.b_interlaced = 0;
+        self.raw.b_fake_interlaced = 0;
+        self
+    }
+
+    /// Whether to signal how each frame is displayed, as given by
+    /// `FrameOptions::pic_struct`.
+    pub fn pic_struct(mut self, enabled: bool) -> Self {
+        self.raw.b_pic_struct = if enabled { 1 } else { 0 };
+        self
+    }
+
+    /// How the views of stereoscopic video are packed into each frame.
+    pub fn frame_packing(mut self, packing: FramePacking) -> Self {
+        self.raw.i_frame_packing = packing as i32;
+        self
+    }
+
+    /// Whether to use CABAC, which compresses better, or CAVLC, which is
+    /// easier to decode.
+    ///
+    /// This overrides the profile, but the baseline profile doesn't allow
+    /// CABAC, so asking for both makes `build` fail.
+    pub fn cabac(mut self, enabled: bool) -> Self {
+        self.cabac = Some(enabled);
+        self
+    }
+
+    /// Sets up the deblocking filter, which smooths the edges of blocks.
+    ///
+    /// The strength and threshold go from -6 to 6, where higher values
+    /// deblock more, and anything else makes `build` fail.
+    pub fn deblock(
+        mut self,
+        enabled: bool,
+        strength: i32,
+        threshold: i32,
+    ) -> Self {
+        self.raw.b_deblocking_filter = if enabled { 1 } else { 0 };
+        self.raw.i_deblocking_filter_alphac0 = strength;
+        self.raw.i_deblocking_filter_beta = threshold;
+        self
+    }
+
+    /// The quantization matrices.
+    ///
+    /// Anything but flat matrices needs the high profile or better, so
+    /// `build` fails if a lower profile was asked for.
+    ///
+    /// ```rust
+    /// # use x264::{Cqm, Matrices, Setup};
+    /// let matrices = Matrices::parse("INTRA4X4_LUMA = 0").unwrap();
+    /// let setup = Setup::default().cqm(Cqm::Custom(matrices));
+    /// ```
+    pub fn cqm(mut self, cqm: Cqm) -> Self {
+        cqm.apply(&mut self.raw);
+        self
+    }
+
+    /// Encodes some ranges of frames at a different quality, like the
+    /// credits at a lower one.
+    ///
+    /// Zones shouldn't overlap, and `build` fails if one doesn't make sense.
+    #[cfg(feature = "std")]
+    pub fn zones(mut self, zones: &[Zone]) -> Self {
+        self.zones = zones.to_vec();
+        self
+    }
+
+    /// The number of previous frames that each P-frame can reference.
+    ///
+    /// More is slower, but compresses better and gives invalidated references
+    /// something to fall back on.
+    pub fn reference_frames(mut self, frames: i32) -> Self {
+        self.raw.i_frame_reference = frames;
+        self
+    }
+
+    /// The maximum number of consecutive B-frames.
+    ///
+    /// Zero turns B-frames off, which is needed for invalidating references.
+    pub fn bframes(mut self, frames: i32) -> Self {
+        self.raw.i_bframe = frames;
+        self
+    }
+
+    /// The number of slices each frame is split into.
+    ///
+    /// Zero lets the other slice settings decide.
+    pub fn slices(mut self, count: i32) -> Self {
+        self.raw.i_slice_count = count;
+        self
+    }
+
+    /// The most bytes a slice may take, including its NAL overhead, which is
+    /// handy for fitting each slice into a network packet.
+    ///
+    /// Zero means there's no limit.
+    pub fn slice_max_size(mut self, bytes: i32) -> Self {
+        self.raw.i_slice_max_size = bytes;
+        self
+    }
+
+    /// The most macroblocks a slice may hold.
+    ///
+    /// Zero means there's no limit.
+    pub fn slice_max_mbs(mut self, mbs: i32) -> Self {
+        self.raw.i_slice_max_mbs = mbs;
+        self
+    }
+
+    /// The fewest macroblocks a slice may hold, unless it ends the frame.
+    pub fn slice_min_mbs(mut self, mbs: i32) -> Self {
+        self.raw.i_slice_min_mbs = mbs;
+        self
+    }
+
+    /// Whether to replace keyframes with a column of intra blocks that moves
+    /// across the video, which avoids the bitrate spike of a keyframe.
+    ///
+    /// Each refresh takes as many frames as the keyframe interval, at most.
+    pub fn intra_refresh(mut self, refresh: bool) -> Self {
+        self.raw.b_intra_refresh = if refresh { 1 } else { 0 };
+        self
+    }
+
+    /// The number of threads used to encode frames.
+    ///
+    /// Zero lets x264 decide, based on the number of processors.
+    pub fn threads(mut self, threads: i32) -> Self {
+        self.raw.i_threads = threads;
+        self
+    }
+
+    /// Whether to split each frame into slices and encode them in parallel,
+    /// instead of encoding several frames at once.
+    ///
+    /// This is slower and a little less efficient, but doesn't add latency.
+    pub fn sliced_threads(mut self, sliced: bool) -> Self {
+        self.raw.b_sliced_threads = if sliced { 1 } else { 0 };
+        self
+    }
+
+    /// The number of threads used by the lookahead.
+    ///
+    /// Zero lets x264 decide, based on the number of encoding threads.
+    pub fn lookahead_threads(mut self, threads: i32) -> Self {
+        self.raw.i_lookahead_threads = threads;
+        self
+    }
+
+    /// Whether the output should be the same regardless of the thread count.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.raw.b_deterministic = if deterministic { 1 } else { 0 };
+        self
+    }
+
+    /// The least important messages x264 will still log.
+    pub fn log_level(mut self, level: LogLevel) -> Self {
+        self.raw.i_log_level = level as i32;
+        self
+    }
+
+    /// Sends x264's log messages to the given function, instead of stderr.
+    ///
+    /// The function may be called from several threads at once.
+    #[cfg(feature = "std")]
+    pub fn logger<F>(mut self, logger: F) -> Self
+    where
+        F: Fn(LogLevel, &str) + Send + Sync + 'static,
+    {
+        self.logger = Some(Box::new(Box::new(logger)));
+        self
+    }
+
+    /// Sends x264's log messages to the `log` crate, under the `x264` target.
+    ///
+    /// The log level is set to match `log::max_level`.
+    #[cfg(feature = "log")]
+    pub fn log_to_crate(self) -> Self {
+        self.log_level(logging::log_to_crate_level())
+            .logger(logging::log_to_crate)
+    }
+
+    /// Hands over each unit as soon as it's encoded, so that it can be sent
+    /// before the rest of the frame is done.
+    ///
+    /// This turns on sliced threads, since it doesn't work with frame-based
+    /// threads, and it's best combined with a slice size limit. The units of
+    /// a frame may arrive out of order, and from several threads at once.
+    ///
+    /// With this, the data returned by the encoder is always empty, except
+    /// for `Encoder::headers`. The headers it returns never go to the
+    /// callback, but any headers repeated within the stream do.
+    #[cfg(feature = "std")]
+    pub fn on_unit<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Unit) + Send + Sync + 'static,
+    {
+        self.raw.b_sliced_threads = 1;
+        self.on_unit = Some(Box::new(Box::new(callback)));
+        self
+    }
+
+    /// Sets an option by the name x264's command line gives it.
+    ///
+    /// This reaches the options that don't have their own methods, but it
+    /// doesn't understand the presets, tunes or profiles.
+    ///
+    /// ```rust
+    /// # use x264::Setup;
+    /// let setup = Setup::default().option("keyint", "60").unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn option(mut self, name: &str, value: &str) -> Result<Self> {
+        self.parse(name, Some(value))?;
+        Ok(self)
+    }
+
+    /// Sets several options, given in the same `key=val:key=val` syntax that
+    /// `--x264-params` accepts.
+    ///
+    /// An option without a value, like `no-cabac`, is treated as a flag.
+    ///
+    /// ```rust
+    /// # use x264::Setup;
+    /// let setup = Setup::default().options("keyint=60:no-cabac").unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn options(mut self, options: &str) -> Result<Self> {
+        for option in options.split(':').filter(|o| !o.is_empty()) {
+            let mut parts = option.splitn(2, '=');
+            let name = parts.next().unwrap();
+            self.parse(name, parts.next())?;
+        }
+
+        Ok(self)
+    }
+
+    #[cfg(feature = "std")]
+    fn parse(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        let name = CString::new(name).map_err(|_| Error::UnknownOption)?;
+        let value = match value {
+            Some(value) =>
+                Some(CString::new(value).map_err(|_| Error::BadValue)?),
+            None => None,
+        };
+
+        let err = unsafe {
+            x264_param_parse(
                 &mut self.raw,
-                b"main\0" as *const u8 as *const i8
-            );
+                name.as_ptr(),
+                value.as_ref().map_or(ptr::null(), |v| v.as_ptr())
+            )
+        };
+
+        if err == 0 {
+            Ok(())
+        } else if err == X264_PARAM_BAD_NAME as i32 {
+            Err(Error::UnknownOption)
+        } else {
+            Err(Error::BadValue)
         }
+    }
+
+    /// Restricts the encoder to the given profile.
+    ///
+    /// The profile is applied when the encoder is built, at which point it is
+    /// checked against the colorspace and bit depth of the input.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
         self
     }
 
+    /// The lowest profile, with guaranteed compatibility with all decoders.
+    pub fn baseline(self) -> Self {
+        self.profile(Profile::Baseline)
+    }
+
+    /// A useless middleground between the baseline and high profiles.
+    pub fn main(self) -> Self {
+        self.profile(Profile::Main)
+    }
+
     /// The highest profile, which almost all encoders support.
-    pub fn high(mut self) -> Self {
-        unsafe {
-            x264_param_apply_profile(
-                &mut self.raw,
-                b"high\0" as *const u8 as *const i8
-            );
-        }
+    pub fn high(self) -> Self {
+        self.profile(Profile::High)
+    }
+
+    /// Requires the stream to be decodable at the given level.
+    ///
+    /// When no level is given, x264 picks one by itself.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self.raw.i_level_idc = level.idc();
         self
     }
 
+    /// The lowest level that can hold a video of the given size, considering
+    /// the framerate, reference frames and VBV settings chosen so far.
+    ///
+    /// Returns `None` if not even the highest level is enough.
+    pub fn minimum_level(&self, width: i32, height: i32) -> Option<Level> {
+        let mut raw = self.raw;
+        raw.i_width = width;
+        raw.i_height = height;
+        Level::minimum(&Demand::from_raw(&raw, self.profile))
+    }
+
     /// Build the encoder.
+    ///
+    /// Fails if the profile can't encode the given colorspace or bit depth,
+    /// if it doesn't allow CABAC or the matrices when those were asked for,
+    /// if the video can't fit in the requested level, if a zone doesn't make
+    /// sense, if the size doesn't work with the interlacing or cropping, or
+    /// if Blu-ray or AVC-Intra don't allow the format.
     pub fn build<C>(
         mut self,
         csp: C,
@@ -116,18 +713,157 @@ impl Setup {
     where
         C: Into<Encoding>,
     {
-        self.raw.i_csp = csp.into().into_raw();
+        let csp = csp.into();
+
+        self.raw.i_csp = csp.into_raw();
         self.raw.i_width = width;
         self.raw.i_height = height;
 
+        let interlaced = self.raw.b_interlaced != 0
+                      || self.raw.b_fake_interlaced != 0;
+
+        if interlaced && !image::interlaceable(csp, height) {
+            return Err(Error::Size);
+        }
+
+        if let Some(compliance) = self.compliance {
+            compliance.check(&self.raw, csp)?;
+        }
+
+        if self.compliance == Some(Compliance::Bluray) {
+            // Blu-ray needs a keyframe at least every second.
+            let (num, den) = (self.raw.i_fps_num, self.raw.i_fps_den);
+            let second = ((num + den - 1) / den) as i32;
+            self.raw.i_keyint_max = cmp::min(self.raw.i_keyint_max, second);
+        }
+
+        if let Some((num, den)) = self.display_aspect {
+            let (width, height) = self.cropped_size()?;
+
+            if num <= 0 || den <= 0 {
+                return Err(Error::BadValue);
+            }
+
+            // Each pixel is as much wider than tall as the display is
+            // compared to the grid of pixels.
+            let sar_width = num as i64 * height as i64;
+            let sar_height = den as i64 * width as i64;
+            let gcd = gcd(sar_width, sar_height);
+
+            self.raw.vui.i_sar_width = (sar_width / gcd) as i32;
+            self.raw.vui.i_sar_height = (sar_height / gcd) as i32;
+        }
+
+        if let Some(profile) = self.profile {
+            if !profile.supports(csp) {
+                return Err(Error::Profile);
+            }
+
+            // Otherwise x264 would quietly go back to flat matrices.
+            let flat = self.raw.i_cqm_preset == X264_CQM_FLAT as i32;
+            let below_high = profile == Profile::Baseline
+                          || profile == Profile::Main;
+
+            if below_high && !flat {
+                return Err(Error::Profile);
+            }
+
+            let err = unsafe {
+                x264_param_apply_profile(&mut self.raw, profile.to_cstr())
+            };
+
+            if err < 0 {
+                return Err(Error::Profile);
+            }
+        }
+
+        if let Some(cabac) = self.cabac {
+            if cabac && self.profile == Some(Profile::Baseline) {
+                return Err(Error::Profile);
+            }
+
+            self.raw.b_cabac = if cabac { 1 } else { 0 };
+        }
+
+        let deblock = [
+            self.raw.i_deblocking_filter_alphac0,
+            self.raw.i_deblocking_filter_beta,
+        ];
+
+        if deblock.iter().any(|&x| x < -6 || x > 6) {
+            return Err(Error::BadValue);
+        }
+
+        if let Some(level) = self.level {
+            let demand = Demand::from_raw(&self.raw, self.profile);
+            if !level.allows(&demand) {
+                return Err(Error::Level);
+            }
+        }
+
+        // This has to outlive the encoder, since x264 points into it.
+        let owned = self.take_owned()?;
+
         let raw = unsafe { x264_encoder_open(&mut self.raw) };
 
         if raw.is_null() {
-            Err(Error)
+            Err(Error::Unknown)
         } else {
-            Ok(unsafe { Encoder::from_raw(raw) })
+            Ok(unsafe { Encoder::from_parts(raw, owned) })
         }
     }
+
+    // The size of the video that's actually shown.
+    fn cropped_size(&self) -> Result<(i32, i32)> {
+        let crop = &self.raw.crop_rect;
+        let width = self.raw.i_width as i64
+            - crop.i_left as i64 - crop.i_right as i64;
+        let height = self.raw.i_height as i64
+            - crop.i_top as i64 - crop.i_bottom as i64;
+
+        if width <= 0 || height <= 0 {
+            Err(Error::Size)
+        } else {
+            Ok((width as i32, height as i32))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn take_owned(&mut self) -> Result<Owned> {
+        let mut owned = Owned::default();
+
+        if !self.zones.is_empty() {
+            let zones = zone::to_string(&self.zones)?;
+            let zones = CString::new(zones).map_err(|_| Error::BadValue)?;
+            self.raw.rc.psz_zones = zones.as_ptr() as *mut i8;
+            owned.zones = Some(zones);
+        }
+
+        if let Some(logger) = self.logger.take() {
+            self.raw.pf_log = Some(logging::log_callback);
+            self.raw.p_log_private = &*logger as *const Logger as *mut c_void;
+            owned.logger = Some(logger);
+        }
+
+        if let Some(on_unit) = self.on_unit.take() {
+            // Set here too, in case sliced threads were turned off later.
+            self.raw.b_sliced_threads = 1;
+            // The callback itself is found through each picture's opaque.
+            self.raw.nalu_process = Some(nalu::unit_callback);
+            owned.on_unit = Some(Box::new(UnitSink::new(*on_unit)));
+        }
+
+        Ok(owned)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn take_owned(&mut self) -> Result<Owned> {
+        Ok(Owned::default())
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
 }
 
 impl Default for Setup {
@@ -138,6 +874,6 @@ impl Default for Setup {
             raw
         };
 
-        Self { raw }
+        Self::from_raw(raw)
     }
 }
@@ -0,0 +1,56 @@
+use Unit;
+use core::ffi::c_void;
+use std::boxed::Box;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::vec::Vec;
+use x264::*;
+
+/// A function that receives each unit as soon as it's encoded.
+pub type UnitCallback = Box<dyn Fn(Unit) + Send + Sync>;
+
+// What each picture's opaque points to.
+pub struct UnitSink {
+    pub callback: UnitCallback,
+    // Set while `Encoder::headers` runs, since it hands back the headers.
+    pub headers: AtomicBool,
+}
+
+impl UnitSink {
+    pub fn new(callback: UnitCallback) -> Self {
+        UnitSink { callback, headers: AtomicBool::new(false) }
+    }
+}
+
+pub unsafe extern "C" fn unit_callback(
+    h: *mut x264_t,
+    nal: *mut x264_nal_t,
+    opaque: *mut c_void,
+) {
+    // The headers can be written before any picture has given an opaque.
+    if opaque.is_null() {
+        return;
+    }
+
+    let sink = &*(opaque as *const UnitSink);
+
+    if sink.headers.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let callback = &sink.callback;
+
+    // The size that x264 asks for, so that it has room to escape the data.
+    let size = (*nal).i_payload as usize * 3 / 2 + 5 + 64;
+    let mut buf = Vec::<u8>::with_capacity(size);
+
+    // A copy is escaped, so that x264's own unit keeps pointing at data that
+    // outlives this buffer.
+    let mut copy = *nal;
+    x264_nal_encode(h, buf.as_mut_ptr(), &mut copy);
+
+    let unit = Unit::from_raw(copy);
+
+    // Unwinding into C is undefined behaviour.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| callback(unit)));
+}
@@ -0,0 +1,24 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How the two views of stereoscopic video are packed into each frame.
+pub enum FramePacking {
+    /// The video isn't stereoscopic, and nothing is signalled.
+    None = -1,
+    /// The views alternate pixel by pixel, like a checkerboard.
+    Checkerboard = 0,
+    /// The views alternate column by column.
+    Columns = 1,
+    /// The views alternate row by row.
+    Rows = 2,
+    /// The left view is on the left, and the right view on the right.
+    SideBySide = 3,
+    /// The left view is on top, and the right view below.
+    TopBottom = 4,
+    /// The views alternate frame by frame.
+    Alternate = 5,
+    /// The video is explicitly flat.
+    Mono = 6,
+    /// The views are tiled, for 720p views in a 1080p frame.
+    Tiled = 7,
+}
@@ -0,0 +1,66 @@
+use {Colorspace, Encoding, Modifier};
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// An H.264 profile, which restricts the features the encoder may use.
+pub enum Profile {
+    /// The lowest profile, with guaranteed compatibility with all decoders.
+    Baseline,
+    /// A useless middleground between the baseline and high profiles.
+    Main,
+    /// The highest 8-bit 4:2:0 profile, which almost all decoders support.
+    High,
+    /// Like the high profile, but with up to 10 bits per sample.
+    High10,
+    /// Like the high 10 profile, but with 4:2:2 chroma subsampling.
+    High422,
+    /// Like the high 4:2:2 profile, but with 4:4:4 chroma and lossless coding.
+    High444Predictive,
+}
+
+impl Profile {
+    /// Whether the profile can encode images of the given encoding.
+    pub fn supports<E: Into<Encoding>>(self, encoding: E) -> bool {
+        use self::Colorspace::*;
+        use self::Profile::*;
+
+        let encoding = encoding.into();
+
+        let chroma = match encoding.colorspace() {
+            I420 | YV12 | NV12 | NV21       => 0,
+            I422 | YV16 | NV16 | V210       => 1,
+            #[cfg(feature = "yuyv")]
+            YUYV | UYVY                     => 1,
+            I444 | YV24 | BGR | BGRA | RGB  => 2,
+        };
+
+        let high_depth = high_depth(encoding);
+
+        match self {
+            Baseline | Main | High => chroma == 0 && !high_depth,
+            High10                 => chroma == 0,
+            High422                => chroma <= 1,
+            High444Predictive      => true,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn to_cstr(self) -> *const i8 {
+        use self::Profile::*;
+
+        (match self {
+            Baseline          => b"baseline\0" as *const u8,
+            Main              => b"main\0" as *const u8,
+            High              => b"high\0" as *const u8,
+            High10            => b"high10\0" as *const u8,
+            High422           => b"high422\0" as *const u8,
+            High444Predictive => b"high444\0" as *const u8,
+        }) as *const i8
+    }
+}
+
+// Whether the encoding has more than 8 bits per sample.
+pub(super) fn high_depth(encoding: Encoding) -> bool {
+    encoding.has(Modifier::HighDepth)
+        || encoding.colorspace() == Colorspace::V210
+}
@@ -0,0 +1,16 @@
+use x264::*;
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// How adaptive quantization spreads bits across each frame.
+pub enum AqMode {
+    /// Every macroblock gets the same quantizer.
+    None = X264_AQ_NONE as i32,
+    /// Flat areas get more bits, based on their variance.
+    Variance = X264_AQ_VARIANCE as i32,
+    /// Like `Variance`, but the strength adapts to each frame.
+    AutoVariance = X264_AQ_AUTOVARIANCE as i32,
+    /// Like `AutoVariance`, but biased towards dark scenes.
+    AutoVarianceBiased = X264_AQ_AUTOVARIANCE_BIASED as i32,
+}
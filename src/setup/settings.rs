@@ -0,0 +1,278 @@
+use super::Setup;
+use super::compliance::Compliance;
+use core::result;
+use serde::de::Error;
+use serde::ser;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::vec::Vec;
+use x264::*;
+use {Level, Profile, Zone};
+
+// Every plain-data field of `x264_param_t` that affects the output.
+//
+// The pointers (log callbacks, file names and so on) are left out, since they
+// wouldn't mean anything when reloaded.
+macro_rules! params {
+    ($($name:ident: $ty:ty = $($field:ident).+;)*) => {
+        #[derive(Serialize, Deserialize)]
+        struct Params {
+            $($name: $ty,)*
+            cqm_8iy: Vec<u8>,
+            cqm_8py: Vec<u8>,
+            cqm_8ic: Vec<u8>,
+            cqm_8pc: Vec<u8>,
+        }
+
+        impl Params {
+            fn load(raw: &x264_param_t) -> Self {
+                Params {
+                    $($name: raw.$($field).+,)*
+                    cqm_8iy: raw.cqm_8iy.to_vec(),
+                    cqm_8py: raw.cqm_8py.to_vec(),
+                    cqm_8ic: raw.cqm_8ic.to_vec(),
+                    cqm_8pc: raw.cqm_8pc.to_vec(),
+                }
+            }
+
+            fn store(&self, raw: &mut x264_param_t) -> bool {
+                let cqms = [&self.cqm_8iy, &self.cqm_8py,
+                            &self.cqm_8ic, &self.cqm_8pc];
+
+                if cqms.iter().any(|cqm| cqm.len() != 64) {
+                    return false;
+                }
+
+                $(raw.$($field).+ = self.$name;)*
+                raw.cqm_8iy.copy_from_slice(&self.cqm_8iy);
+                raw.cqm_8py.copy_from_slice(&self.cqm_8py);
+                raw.cqm_8ic.copy_from_slice(&self.cqm_8ic);
+                raw.cqm_8pc.copy_from_slice(&self.cqm_8pc);
+                true
+            }
+        }
+    };
+}
+
+params! {
+    threads: i32 = i_threads;
+    lookahead_threads: i32 = i_lookahead_threads;
+    sliced_threads: i32 = b_sliced_threads;
+    deterministic: i32 = b_deterministic;
+    cpu_independent: i32 = b_cpu_independent;
+    sync_lookahead: i32 = i_sync_lookahead;
+
+    width: i32 = i_width;
+    height: i32 = i_height;
+    csp: i32 = i_csp;
+    level_idc: i32 = i_level_idc;
+    frame_total: i32 = i_frame_total;
+    nal_hrd: i32 = i_nal_hrd;
+
+    sar_height: i32 = vui.i_sar_height;
+    sar_width: i32 = vui.i_sar_width;
+    overscan: i32 = vui.i_overscan;
+    vidformat: i32 = vui.i_vidformat;
+    fullrange: i32 = vui.b_fullrange;
+    colorprim: i32 = vui.i_colorprim;
+    transfer: i32 = vui.i_transfer;
+    colmatrix: i32 = vui.i_colmatrix;
+    chroma_loc: i32 = vui.i_chroma_loc;
+
+    frame_reference: i32 = i_frame_reference;
+    dpb_size: i32 = i_dpb_size;
+    keyint_max: i32 = i_keyint_max;
+    keyint_min: i32 = i_keyint_min;
+    scenecut_threshold: i32 = i_scenecut_threshold;
+    intra_refresh: i32 = b_intra_refresh;
+
+    bframe: i32 = i_bframe;
+    bframe_adaptive: i32 = i_bframe_adaptive;
+    bframe_bias: i32 = i_bframe_bias;
+    bframe_pyramid: i32 = i_bframe_pyramid;
+    open_gop: i32 = b_open_gop;
+    bluray_compat: i32 = b_bluray_compat;
+    avcintra_class: i32 = i_avcintra_class;
+
+    deblocking_filter: i32 = b_deblocking_filter;
+    deblocking_filter_alphac0: i32 = i_deblocking_filter_alphac0;
+    deblocking_filter_beta: i32 = i_deblocking_filter_beta;
+
+    cabac: i32 = b_cabac;
+    cabac_init_idc: i32 = i_cabac_init_idc;
+    interlaced: i32 = b_interlaced;
+    constrained_intra: i32 = b_constrained_intra;
+
+    cqm_preset: i32 = i_cqm_preset;
+    cqm_4iy: [u8; 16] = cqm_4iy;
+    cqm_4py: [u8; 16] = cqm_4py;
+    cqm_4ic: [u8; 16] = cqm_4ic;
+    cqm_4pc: [u8; 16] = cqm_4pc;
+
+    log_level: i32 = i_log_level;
+    full_recon: i32 = b_full_recon;
+
+    intra: u32 = analyse.intra;
+    inter: u32 = analyse.inter;
+    transform_8x8: i32 = analyse.b_transform_8x8;
+    weighted_pred: i32 = analyse.i_weighted_pred;
+    weighted_bipred: i32 = analyse.b_weighted_bipred;
+    direct_mv_pred: i32 = analyse.i_direct_mv_pred;
+    chroma_qp_offset: i32 = analyse.i_chroma_qp_offset;
+    me_method: i32 = analyse.i_me_method;
+    me_range: i32 = analyse.i_me_range;
+    mv_range: i32 = analyse.i_mv_range;
+    mv_range_thread: i32 = analyse.i_mv_range_thread;
+    subpel_refine: i32 = analyse.i_subpel_refine;
+    chroma_me: i32 = analyse.b_chroma_me;
+    mixed_references: i32 = analyse.b_mixed_references;
+    trellis: i32 = analyse.i_trellis;
+    fast_pskip: i32 = analyse.b_fast_pskip;
+    dct_decimate: i32 = analyse.b_dct_decimate;
+    noise_reduction: i32 = analyse.i_noise_reduction;
+    psy_rd: f32 = analyse.f_psy_rd;
+    psy_trellis: f32 = analyse.f_psy_trellis;
+    psy: i32 = analyse.b_psy;
+    mb_info: i32 = analyse.b_mb_info;
+    mb_info_update: i32 = analyse.b_mb_info_update;
+    luma_deadzone: [i32; 2] = analyse.i_luma_deadzone;
+    psnr: i32 = analyse.b_psnr;
+    ssim: i32 = analyse.b_ssim;
+
+    rc_method: i32 = rc.i_rc_method;
+    qp_constant: i32 = rc.i_qp_constant;
+    qp_min: i32 = rc.i_qp_min;
+    qp_max: i32 = rc.i_qp_max;
+    qp_step: i32 = rc.i_qp_step;
+    bitrate: i32 = rc.i_bitrate;
+    rf_constant: f32 = rc.f_rf_constant;
+    rf_constant_max: f32 = rc.f_rf_constant_max;
+    rate_tolerance: f32 = rc.f_rate_tolerance;
+    vbv_max_bitrate: i32 = rc.i_vbv_max_bitrate;
+    vbv_buffer_size: i32 = rc.i_vbv_buffer_size;
+    vbv_buffer_init: f32 = rc.f_vbv_buffer_init;
+    ip_factor: f32 = rc.f_ip_factor;
+    pb_factor: f32 = rc.f_pb_factor;
+    aq_mode: i32 = rc.i_aq_mode;
+    aq_strength: f32 = rc.f_aq_strength;
+    mb_tree: i32 = rc.b_mb_tree;
+    lookahead: i32 = rc.i_lookahead;
+    stat_write: i32 = rc.b_stat_write;
+    stat_read: i32 = rc.b_stat_read;
+    qcompress: f32 = rc.f_qcompress;
+    qblur: f32 = rc.f_qblur;
+    complexity_blur: f32 = rc.f_complexity_blur;
+
+    crop_left: u32 = crop_rect.i_left;
+    crop_top: u32 = crop_rect.i_top;
+    crop_right: u32 = crop_rect.i_right;
+    crop_bottom: u32 = crop_rect.i_bottom;
+
+    frame_packing: i32 = i_frame_packing;
+    aud: i32 = b_aud;
+    repeat_headers: i32 = b_repeat_headers;
+    annexb: i32 = b_annexb;
+    sps_id: i32 = i_sps_id;
+    vfr_input: i32 = b_vfr_input;
+    pulldown: i32 = b_pulldown;
+    fps_num: u32 = i_fps_num;
+    fps_den: u32 = i_fps_den;
+    timebase_num: u32 = i_timebase_num;
+    timebase_den: u32 = i_timebase_den;
+    tff: i32 = b_tff;
+    pic_struct: i32 = b_pic_struct;
+    fake_interlaced: i32 = b_fake_interlaced;
+    stitchable: i32 = b_stitchable;
+
+    slice_max_size: i32 = i_slice_max_size;
+    slice_max_mbs: i32 = i_slice_max_mbs;
+    slice_min_mbs: i32 = i_slice_min_mbs;
+    slice_count: i32 = i_slice_count;
+    slice_count_max: i32 = i_slice_count_max;
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    profile: Option<Profile>,
+    level: Option<Level>,
+    #[serde(default)]
+    cabac: Option<bool>,
+    #[serde(default)]
+    compliance: Option<Compliance>,
+    #[serde(default)]
+    display_aspect: Option<(i32, i32)>,
+    #[serde(default)]
+    zones: Vec<Zone>,
+    params: Params,
+}
+
+// The first of the strings that `Params` can't hold, which only `option` can
+// set, and which would otherwise be dropped without a word.
+fn unsaved(raw: &x264_param_t) -> Option<&'static str> {
+    // The default that x264 points both stats files at.
+    const STATS: &[u8] = b"x264_2pass.log";
+
+    let custom = |path: *mut c_char| {
+        !path.is_null()
+            && unsafe { CStr::from_ptr(path) }.to_bytes() != STATS
+    };
+
+    if !raw.rc.psz_zones.is_null() {
+        Some("zones")
+    } else if !raw.psz_cqm_file.is_null() {
+        Some("cqmfile")
+    } else if !raw.psz_dump_yuv.is_null() {
+        Some("dump-yuv")
+    } else if raw.rc.b_stat_write != 0 && custom(raw.rc.psz_stat_out) {
+        Some("stats")
+    } else if raw.rc.b_stat_read != 0 && custom(raw.rc.psz_stat_in) {
+        Some("stats")
+    } else {
+        None
+    }
+}
+
+impl Serialize for Setup {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if let Some(name) = unsaved(&self.raw) {
+            let message = format!("`{}` can't be saved", name);
+            return Err(<S::Error as ser::Error>::custom(message));
+        }
+
+        Saved {
+            profile: self.profile,
+            level: self.level,
+            cabac: self.cabac,
+            compliance: self.compliance,
+            display_aspect: self.display_aspect,
+            zones: self.zones.clone(),
+            params: Params::load(&self.raw),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Setup {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let saved = Saved::deserialize(deserializer)?;
+        let mut setup = Setup::default();
+
+        if !saved.params.store(&mut setup.raw) {
+            return Err(D::Error::custom("8x8 matrices need 64 entries"));
+        }
+
+        setup.profile = saved.profile;
+        setup.level = saved.level;
+        setup.cabac = saved.cabac;
+        setup.compliance = saved.compliance;
+        setup.display_aspect = saved.display_aspect;
+        setup.zones = saved.zones;
+        Ok(setup)
+    }
+}
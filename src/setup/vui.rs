@@ -0,0 +1,31 @@
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// Whether the edges of the video may be hidden by the display.
+pub enum Overscan {
+    /// Nothing is signalled.
+    Undefined = 0,
+    /// The whole picture should be shown.
+    Show = 1,
+    /// The edges may be cropped, like on old televisions.
+    Crop = 2,
+}
+
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(i32)]
+/// The analog format that the video came from.
+pub enum VideoFormat {
+    /// Component video.
+    Component = 0,
+    /// PAL.
+    Pal = 1,
+    /// NTSC.
+    Ntsc = 2,
+    /// SECAM.
+    Secam = 3,
+    /// MAC.
+    Mac = 4,
+    /// Nothing is signalled.
+    Undefined = 5,
+}
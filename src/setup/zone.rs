@@ -0,0 +1,196 @@
+use {Error, Result};
+use std::fmt::Write;
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Rate {
+    Qp(i32),
+    Bitrate(f32),
+}
+
+/// A range of frames that's encoded at a different quality.
+///
+/// ```rust
+/// # use x264::{Setup, Zone};
+/// let zones = [
+///     Zone::qp(0, 47, 18),
+///     Zone::bitrate(9000, 9600, 0.5).option("psy-rd", "0.5:0"),
+/// ];
+/// let setup = Setup::default().zones(&zones);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Zone {
+    start: i32,
+    end: i32,
+    rate: Rate,
+    options: Vec<(String, String)>,
+}
+
+impl Zone {
+    /// Encodes the frames from `start` to `end` (inclusive) with a fixed
+    /// quantizer.
+    pub fn qp(start: i32, end: i32, qp: i32) -> Self {
+        Self::new(start, end, Rate::Qp(qp))
+    }
+
+    /// Multiplies the bitrate of the frames from `start` to `end`
+    /// (inclusive) by some factor.
+    pub fn bitrate(start: i32, end: i32, factor: f32) -> Self {
+        Self::new(start, end, Rate::Bitrate(factor))
+    }
+
+    /// Changes another option within the zone, like `deblock` or `psy-rd`.
+    ///
+    /// Only some options can change partway through, and x264 refuses to
+    /// start if any of the others are given.
+    pub fn option(mut self, name: &str, value: &str) -> Self {
+        self.options.push((name.into(), value.into()));
+        self
+    }
+
+    /// The first frame.
+    pub fn start(&self) -> i32 {
+        self.start
+    }
+
+    /// The last frame.
+    pub fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn new(start: i32, end: i32, rate: Rate) -> Self {
+        Zone { start, end, rate, options: Vec::new() }
+    }
+
+    // Writes the zone in the `start,end,q=N,name=value` syntax.
+    fn write(&self, out: &mut String) -> Result<()> {
+        if self.start < 0 || self.end < self.start {
+            return Err(Error::BadValue);
+        }
+
+        let _ = write!(out, "{},{},", self.start, self.end);
+
+        let _ = match self.rate {
+            Rate::Qp(qp) if qp >= 0 => write!(out, "q={}", qp),
+            Rate::Bitrate(b) if b > 0.0 => write!(out, "b={}", b),
+            _ => return Err(Error::BadValue),
+        };
+
+        for &(ref name, ref value) in &self.options {
+            // These would be taken for the separators of the list.
+            let bad = |c: char| c == ',' || c == '/' || c == '\0';
+
+            if name.is_empty() || name.contains('=') || name.contains(bad)
+                || value.contains(bad)
+            {
+                return Err(Error::BadValue);
+            }
+
+            let _ = write!(out, ",{}={}", name, value);
+        }
+
+        Ok(())
+    }
+}
+
+// Joins the zones into the string that x264 parses.
+pub fn to_string(zones: &[Zone]) -> Result<String> {
+    let mut out = String::new();
+
+    for (i, zone) in zones.iter().enumerate() {
+        if i > 0 {
+            out.push('/');
+        }
+
+        zone.write(&mut out)?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single() {
+        let zones = [Zone::qp(0, 47, 18)];
+        assert_eq!(to_string(&zones).unwrap(), "0,47,q=18");
+    }
+
+    #[test]
+    fn several() {
+        let zones = [Zone::qp(0, 47, 18), Zone::bitrate(9000, 9600, 0.5)];
+        assert_eq!(to_string(&zones).unwrap(), "0,47,q=18/9000,9600,b=0.5");
+    }
+
+    #[test]
+    fn options() {
+        let zones = [
+            Zone::bitrate(10, 20, 1.5)
+                .option("psy-rd", "0.5")
+                .option("deblock", "-1"),
+            Zone::qp(30, 40, 0).option("trellis", "0"),
+        ];
+        assert_eq!(
+            to_string(&zones).unwrap(),
+            "10,20,b=1.5,psy-rd=0.5,deblock=-1/30,40,q=0,trellis=0"
+        );
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(to_string(&[]).unwrap(), "");
+    }
+
+    #[test]
+    fn bad_range() {
+        let bad = [Zone::qp(-1, 10, 20), Zone::qp(10, 9, 20)];
+
+        for zone in &bad {
+            assert_eq!(to_string(&[zone.clone()]), Err(Error::BadValue));
+        }
+    }
+
+    #[test]
+    fn bad_rate() {
+        let bad = [
+            Zone::qp(0, 10, -1),
+            Zone::bitrate(0, 10, 0.0),
+            Zone::bitrate(0, 10, -0.5),
+        ];
+
+        for zone in &bad {
+            assert_eq!(to_string(&[zone.clone()]), Err(Error::BadValue));
+        }
+    }
+
+    #[test]
+    fn bad_options() {
+        let zone = Zone::qp(0, 10, 20);
+        let bad = [
+            ("", "1"),
+            ("psy=rd", "1"),
+            ("psy,rd", "1"),
+            ("psy/rd", "1"),
+            ("psy\0rd", "1"),
+            ("psy-rd", "0.5,0"),
+            ("psy-rd", "0.5/0"),
+            ("psy-rd", "0.5\0"),
+        ];
+
+        for &(name, value) in &bad {
+            let zone = zone.clone().option(name, value);
+            assert_eq!(to_string(&[zone]), Err(Error::BadValue));
+        }
+    }
+
+    #[test]
+    fn later_zones_checked() {
+        let zones = [Zone::qp(0, 10, 20), Zone::qp(20, 10, 20)];
+        assert_eq!(to_string(&zones), Err(Error::BadValue));
+    }
+}
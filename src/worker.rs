@@ -0,0 +1,235 @@
+use {Colorspace, Encoder, Encoding, Error, Image, Packet, Plane, Result};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::vec::Vec;
+
+/// An image that owns its pixel data, so that it can be sent to a `Worker`.
+pub struct Frame {
+    pts: i64,
+    format: Encoding,
+    width: i32,
+    height: i32,
+    planes: Vec<(i32, Vec<u8>)>,
+}
+
+impl Frame {
+    /// Makes a new frame from planes given as `(stride, data)` pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the planes are invalid, just like `Image::new`.
+    pub fn new<E: Into<Encoding>>(
+        pts: i64,
+        format: E,
+        width: i32,
+        height: i32,
+        planes: Vec<(i32, Vec<u8>)>,
+    ) -> Self {
+        let frame = Frame {
+            pts,
+            format: format.into(),
+            width,
+            height,
+            planes,
+        };
+
+        // Validate the planes now, rather than on the worker thread.
+        frame.image();
+        frame
+    }
+
+    /// Makes a new packed BGR frame.
+    pub fn bgr(pts: i64, width: i32, height: i32, data: Vec<u8>) -> Self {
+        let stride = data.len() as i32 / height;
+        Self::new(pts, Colorspace::BGR, width, height, vec![(stride, data)])
+    }
+
+    /// Makes a new packed RGB frame.
+    pub fn rgb(pts: i64, width: i32, height: i32, data: Vec<u8>) -> Self {
+        let stride = data.len() as i32 / height;
+        Self::new(pts, Colorspace::RGB, width, height, vec![(stride, data)])
+    }
+
+    /// Makes a new packed BGRA frame.
+    pub fn bgra(pts: i64, width: i32, height: i32, data: Vec<u8>) -> Self {
+        let stride = data.len() as i32 / height;
+        Self::new(pts, Colorspace::BGRA, width, height, vec![(stride, data)])
+    }
+
+    /// The presentation timestamp.
+    pub fn pts(&self) -> i64 { self.pts }
+
+    /// Borrows the frame as an image.
+    pub fn image(&self) -> Image {
+        let planes = self.planes
+            .iter()
+            .map(|&(stride, ref data)| Plane { stride, data })
+            .collect::<Vec<_>>();
+
+        Image::new(self.format, self.width, self.height, &planes)
+    }
+}
+
+/// Encodes video on a background thread.
+///
+/// Only `finish` drains the delayed frames. Dropping the worker throws them
+/// away, and only waits for the frame being encoded.
+///
+/// ```rust,no_run
+/// # use x264::{Colorspace, Frame, Setup, Worker};
+/// # let encoder = Setup::default().build(Colorspace::RGB, 64, 64).unwrap();
+/// let worker = Worker::spawn(encoder, 8);
+///
+/// for i in 0..60 {
+///     let frame = Frame::rgb(i, 64, 64, vec![0; 64 * 64 * 3]);
+///
+///     // Drop the frame if the encoder is falling behind.
+///     let _ = worker.try_send(frame);
+///
+///     while let Some(result) = worker.try_recv() {
+///         // Handle the packet.
+///     }
+/// }
+///
+/// let (encoder, rest) = worker.finish();
+///
+/// for result in rest {
+///     // Handle the delayed frames.
+/// }
+/// ```
+pub struct Worker {
+    frames: Option<SyncSender<Frame>>,
+    // Dropped before joining, so that the thread stops early.
+    packets: Option<Receiver<Result<Packet>>>,
+    thread: Option<JoinHandle<Encoder>>,
+    width: i32,
+    height: i32,
+    encoding: Encoding,
+}
+
+impl Worker {
+    /// Moves the encoder to a new thread, which queues up to `bound` frames.
+    pub fn spawn(encoder: Encoder, bound: usize) -> Self {
+        let (width, height) = (encoder.width(), encoder.height());
+        let encoding = encoder.encoding();
+
+        let (frames, rx) = mpsc::sync_channel::<Frame>(bound);
+        let (tx, packets) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            let mut encoder = encoder;
+
+            for frame in rx {
+                let result = match encoder.encode(frame.pts, frame.image()) {
+                    Ok((data, picture)) => match Packet::new(&data, &picture) {
+                        Some(packet) => Ok(packet),
+                        // Nothing comes out while the encoder is filling up.
+                        None => continue,
+                    },
+                    Err(err) => Err(err),
+                };
+
+                if tx.send(result).is_err() {
+                    return encoder;
+                }
+            }
+
+            for result in encoder.packets() {
+                if tx.send(result).is_err() {
+                    break;
+                }
+            }
+
+            encoder
+        });
+
+        Worker {
+            frames: Some(frames),
+            packets: Some(packets),
+            thread: Some(thread),
+            width,
+            height,
+            encoding,
+        }
+    }
+
+    /// Queues a frame, waiting for space in the queue if it's full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the frame and the encoder
+    /// regarding width, height or colorspace.
+    pub fn send(&self, frame: Frame) -> Result<()> {
+        self.check(&frame);
+        self.sender()
+            .send(frame)
+            .map_err(|_| Error::Unknown)
+    }
+
+    /// Queues a frame without waiting, giving it back if the queue is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is a mismatch between the frame and the encoder
+    /// regarding width, height or colorspace.
+    pub fn try_send(&self, frame: Frame) -> ::core::result::Result<(), Frame> {
+        self.check(&frame);
+        self.sender()
+            .try_send(frame)
+            .map_err(|err| match err {
+                TrySendError::Full(frame) => frame,
+                TrySendError::Disconnected(frame) => frame,
+            })
+    }
+
+    /// Takes the next packet, if there is one yet.
+    pub fn try_recv(&self) -> Option<Result<Packet>> {
+        self.receiver().try_recv().ok()
+    }
+
+    /// Waits for the next packet.
+    ///
+    /// Returns `None` if the worker has stopped.
+    pub fn recv(&self) -> Option<Result<Packet>> {
+        self.receiver().recv().ok()
+    }
+
+    /// Stops taking frames and waits for the encoder to finish, returning
+    /// the encoder and every packet that hasn't been received yet.
+    pub fn finish(mut self) -> (Encoder, Vec<Result<Packet>>) {
+        self.frames = None;
+
+        let thread = self.thread.take().unwrap();
+        let encoder = match thread.join() {
+            Ok(encoder) => encoder,
+            Err(panic) => ::std::panic::resume_unwind(panic),
+        };
+
+        (encoder, self.receiver().try_iter().collect())
+    }
+
+    fn sender(&self) -> &SyncSender<Frame> {
+        self.frames.as_ref().unwrap()
+    }
+
+    fn receiver(&self) -> &Receiver<Result<Packet>> {
+        self.packets.as_ref().unwrap()
+    }
+
+    fn check(&self, frame: &Frame) {
+        assert_eq!(frame.width, self.width);
+        assert_eq!(frame.height, self.height);
+        assert_eq!(frame.format, self.encoding);
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.frames = None;
+        self.packets = None;
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}